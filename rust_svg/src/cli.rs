@@ -0,0 +1,481 @@
+use std::fs::File;
+use std::io::{Read, Write};
+
+use indoc::indoc;
+
+use super::*;
+use super::regex::{regex_parse, regex_find};
+
+/*----------------------------------------------------------------------
+Command line
+
+Options are parsed into an Options, by which cli_lsys loads and chooses
+LSys and cli_run draws them, so that all can be used and tested without
+a process.  Nothing here prints: what was loaded and what was skipped
+are returned, for main to report.  Input is json in the
+form of lsys_examples.json, which is built in and used when no files
+are given, text as in text.rs for files ending in ".lsys", or Fractint
+entries for files ending in ".l".  Errors in options are Usage errors.
+*/
+
+/// The help text for -h
+pub static USAGE:&str = indoc! {r#"
+    Usage: rust_svg [options] [file ...]
+
+    Draw Lindenmayer systems read from json files, "-" for stdin.
+    Files ending in .lsys are read as text, and those ending in .l as
+    Fractint L-systems.  With no files the built in examples are drawn.
+
+    Options:
+      -o, --output PATH   write to PATH, "-" for stdout
+                          (default lsys_examples.html, or the format's
+                          extension)
+      -t, --title TEXT    only LSys whose title contains TEXT, ignoring case
+      -m, --match REGEX   only LSys whose title matches REGEX
+      -n, --order N,...   orders to draw, in place of those of each LSys;
+                          each from 0 to 100, fewer than 4 are filled
+                          in automatically
+      -f, --format FMT    html, svg, ps, eps, pdf, png, obj, stl, gcode,
+                          hpgl or lsys
+                          (default from the output path, or html)
+      -1, --figure        draw only the figure, without the page layout
+      -d, --figures DIR   write each order of each LSys to DIR as an svg
+                          file of its own, cropped to the figure
+      -h, --help          show this help
+
+    Options for png:
+          --dpi N         resolution, in pixels per inch, up to 600
+                          (default 96)
+          --stroke-width N
+                          width of lines, in pixels at 96 dpi (default 1.5)
+          --background C  an svg color, or none for transparent (default white)
+          --aliased       without anti-aliasing
+
+    Options for obj and stl:
+          --tubes         obj as a mesh of tubes around the lines, as stl
+                          always is, rather than as the lines themselves
+          --ascii         stl as text, rather than binary
+
+    Options for gcode and hpgl:
+          --size W,H      area to fit the figure to, in mm (default 180,267)
+          --feed D,T      feed rates drawing and travelling, in mm/minute
+                          (default 1000,3000); hpgl uses only D
+          --pen-up CMD    G-code to lift the pen (default "G0 Z5")
+          --pen-down CMD  G-code to lower the pen (default "G0 Z0")
+          --in-order      draw paths in turtle order, without reordering
+                          them for less travel
+
+    The svg, eps, png, obj, stl, gcode and hpgl formats hold one LSys, so
+    select just one.  The ps and pdf formats have a page for each, and the lsys
+    format writes the selected systems as text.
+"#};
+
+/// The built in examples, as json
+pub static EXAMPLES:&str = include_str!("lsys_examples.json");
+
+/// What the output is written as, from --format or the output name
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    /// Pages of figures, in html
+    Html,
+    /// One page, in svg
+    Svg,
+    /// PostScript
+    Ps,
+    /// One page, in encapsulated PostScript
+    Eps,
+    /// Pdf
+    Pdf,
+    /// One page, in png
+    Png,
+    /// Wavefront OBJ, in 3D
+    Obj,
+    /// STL, in 3D
+    Stl,
+    /// G-code, for pen plotters
+    Gcode,
+    /// HP-GL, for pen plotters
+    Hpgl,
+    /// The .lsys text format
+    Lsys,
+}
+
+static FORMATS:[(&str,Format);11] = [
+    ("html", Format::Html),
+    ("svg",  Format::Svg),
+    ("ps",   Format::Ps),
+    ("eps",  Format::Eps),
+    ("pdf",  Format::Pdf),
+    ("png",  Format::Png),
+    ("obj",  Format::Obj),
+    ("stl",  Format::Stl),
+    ("gcode", Format::Gcode),
+    ("hpgl", Format::Hpgl),
+    ("lsys", Format::Lsys),
+];
+
+/// The options of one run, as read by cli_parse
+#[derive(Debug, Clone, PartialEq)]
+pub struct Options {
+    /// Json, text or Fractint files, "-" for stdin
+    pub inputs:  Vec<String>,
+    /// Path, "-" for stdout
+    pub output:  Option<String>,
+    /// Only titles containing this
+    pub title:   Option<String>,
+    /// Only titles matching this regex
+    pub pattern: Option<String>,
+    /// In place of those of each LSys
+    pub orders:  Option<Vec<i32>>,
+    /// Format of the output
+    pub format:  Format,
+    /// One figure, without the page layout
+    pub figure:  bool,
+    /// Directory for an svg of each order
+    pub figures: Option<String>,
+    /// Obj as a tube mesh, rather than polylines
+    pub tubes:   bool,
+    /// Stl as text, rather than binary
+    pub ascii:   bool,
+    /// For png output
+    pub png:     png::PngOptions,
+    /// For gcode and hpgl output
+    pub plot:    plot::PlotOptions,
+    /// Only show USAGE
+    pub help:    bool,
+}
+
+fn format_from_name(name:&str) -> Option<Format> {
+    FORMATS.iter().find(|(n,_)| *n == name).map(|(_,f)| *f)
+}
+
+fn format_ext(format:Format) -> &'static str {
+    FORMATS.iter().find(|(_,f)| *f == format).map_or("html", |(n,_)| n)
+}
+
+fn orders_parse(src:&str) -> Result<Vec<i32>,String> {
+    let orders = src.split(',')
+        .map(|s| s.trim().parse::<i32>().ok().filter(|o| (0..=ORDER_MAX).contains(o)))
+        .collect::<Option<Vec<i32>>>()
+        .ok_or_else(|| format!("Orders must be numbers from 0 to {ORDER_MAX}, not '{src}'"))?;
+    if orders.len() > ORDER_BOXES.len() {
+        return Err(format!("At most {} orders, not '{src}'", ORDER_BOXES.len()));
+    }
+    Ok(orders)
+}
+
+// a number of an option, within limits
+fn option_number(name:&str, src:&str, min:f64, max:f64) -> Result<f64,String> {
+    src.parse::<f64>().ok().filter(|v| (min..=max).contains(v))
+        .ok_or_else(|| format!("Option '{name}' must be a number from {min} to {max}, not '{src}'"))
+}
+
+// two numbers of an option, as "A,B", within limits
+fn option_pair(name:&str, src:&str, min:f64, max:f64) -> Result<(f64,f64),String> {
+    let (a,b) = src.split_once(',')
+        .ok_or_else(|| format!("Option '{name}' must be two numbers as A,B, not '{src}'"))?;
+    Ok((option_number(name, a.trim(), min, max)?, option_number(name, b.trim(), min, max)?))
+}
+
+/// Read the options from the command line arguments, without the program name
+pub fn cli_parse(args:&[String]) -> Result<Options,LSysError> {
+    let usage = |why:String| LSysError::Usage(why);
+    let mut opts = Options {
+        inputs:  vec!(),
+        output:  None,
+        title:   None,
+        pattern: None,
+        orders:  None,
+        format:  Format::Html,
+        figure:  false,
+        figures: None,
+        tubes:   false,
+        ascii:   false,
+        png:     png::png_options_new(),
+        plot:    plot::plot_options_new(),
+        help:    false,
+    };
+    let mut format = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        // "--name=value", or "--name value"
+        let (name,inline) = match arg.split_once('=') {
+            Some((n,v)) if n.starts_with("--") => (n, Some(v.to_string())),
+            _ => (arg.as_str(), None),
+        };
+        let mut value = || inline.clone().or_else(|| args.next().cloned())
+            .ok_or_else(|| usage(format!("Option '{name}' needs a value")));
+        match name {
+            "-o" | "--output" => opts.output  = Some(value()?),
+            "-t" | "--title"  => opts.title   = Some(value()?),
+            "-m" | "--match"  => opts.pattern = Some(value()?),
+            "-n" | "--order"  => opts.orders  = Some(orders_parse(&value()?).map_err(usage)?),
+            "-f" | "--format" => {
+                let v = value()?;
+                format = Some(format_from_name(&v)
+                    .ok_or_else(|| usage(format!("Unknown format '{v}'")))?);
+            }
+            "-1" | "--figure" => opts.figure = true,
+            "-d" | "--figures" => opts.figures = Some(value()?),
+            "--dpi" => opts.png.dpi = option_number(name, &value()?, 10.0, 600.0).map_err(usage)?,
+            "--stroke-width" => {
+                opts.png.stroke_width = option_number(name, &value()?, 0.0, 100.0).map_err(usage)?;
+            }
+            "--background" => {
+                let v = value()?;
+                opts.png.background = match v.as_str() {
+                    "none" => None,
+                    _ => Some(color::color_rgb(&v).ok_or_else(|| usage(format!("Unknown color '{v}'")))?),
+                };
+            }
+            "--aliased" => opts.png.antialias = false,
+            "--tubes"   => opts.tubes = true,
+            "--ascii"   => opts.ascii = true,
+            "--size" => {
+                (opts.plot.width, opts.plot.height) = option_pair(name, &value()?, 1.0, 10000.0).map_err(usage)?;
+            }
+            "--feed" => {
+                (opts.plot.draw_feed, opts.plot.travel_feed) = option_pair(name, &value()?, 1.0, 100000.0).map_err(usage)?;
+            }
+            "--pen-up"   => opts.plot.pen_up   = value()?,
+            "--pen-down" => opts.plot.pen_down = value()?,
+            "--in-order" => opts.plot.reorder  = false,
+            "-h" | "--help"   => opts.help = true,
+            "-" => opts.inputs.push(arg.clone()),
+            _ if name.starts_with('-') => return Err(usage(format!("Unknown option '{arg}'"))),
+            _ => opts.inputs.push(arg.clone()),
+        }
+    }
+    if let Some(p) = &opts.pattern {
+        regex_parse(p).map_err(usage)?;
+    }
+    // the format follows the output path, unless given
+    let from_path = opts.output.as_deref()
+        .and_then(|p| p.rsplit_once('.'))
+        .and_then(|(_,ext)| format_from_name(&ext.to_lowercase()));
+    opts.format = format.or(from_path).unwrap_or(Format::Html);
+    if opts.figures.is_some() && (opts.output.is_some() || format.is_some_and(|f| f != Format::Svg)) {
+        return Err(usage("Option '--figures' writes svg files of its own, without '-o' or '-f'".to_string()));
+    }
+    Ok(opts)
+}
+
+/// What loading one source gave, see cli_lsys
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoadReport {
+    /// File name, or "stdin"
+    pub source: String,
+    /// How many LSys were loaded
+    pub loaded: usize,
+    /// The problems found, in order
+    pub notes:  Vec<LoadNote>,
+}
+
+// the summary line, the notes are shown on their own
+impl std::fmt::Display for LoadReport {
+    fn fmt(&self, f:&mut std::fmt::Formatter) -> std::fmt::Result {
+        let errcnt = self.notes.iter().filter(|n| n.error).count();
+        write!(f, "Successfully loaded {} of {} LSys from {}",
+            self.loaded, self.loaded + errcnt, self.source)
+    }
+}
+
+/// The LSys of all inputs which are chosen by the options, and a report for each input
+pub fn cli_lsys(opts:&Options) -> Result<(Vec<LSys>,Vec<LoadReport>),LSysError> {
+    let mut lsysv = vec!();
+    let mut reports = vec!();
+    let mut add = |name:&str, (loaded,notes):(Vec<LSys>,Vec<LoadNote>)| {
+        reports.push(LoadReport { source: name.to_string(), loaded: loaded.len(), notes });
+        lsysv.extend(loaded);
+    };
+    if opts.inputs.is_empty() {
+        let name = "lsys_examples.json";
+        add(name, lsys_from_json(name, EXAMPLES));
+    }
+    for path in &opts.inputs {
+        let mut src = String::new();
+        let r = if path == "-" {
+            std::io::stdin().read_to_string(&mut src)
+        }
+        else {
+            File::open(path).and_then(|mut f| f.read_to_string(&mut src))
+        };
+        r.map_err(|why| LSysError::Io(path.clone(), why))?;
+        let name = if path == "-" { "stdin" } else { path };
+        let loaded = if path.ends_with(".lsys") {
+            text::lsys_from_text(name, &src)
+        }
+        else if path.ends_with(".l") {
+            fractint::lsys_from_fractint(name, &src)
+        }
+        else {
+            lsys_from_json(name, &src)
+        };
+        add(name, loaded);
+    }
+
+    let title = opts.title.as_ref().map(|t| t.to_lowercase());
+    let pattern = match &opts.pattern {
+        Some(p) => Some(regex_parse(p).map_err(LSysError::Usage)?),
+        None    => None,
+    };
+    lsysv.retain(|lsys|
+        title.as_ref().is_none_or(|t| lsys.title.to_lowercase().contains(t))
+        && pattern.as_ref().is_none_or(|re| regex_find(re, &lsys.title)));
+    if let Some(orders) = &opts.orders {
+        for lsys in &mut lsysv {
+            lsys.order = orders.clone();
+        }
+    }
+    Ok((lsysv,reports))
+}
+
+// a file name from a title, lower case letters and digits joined by "_"
+fn file_stem(title:&str) -> String {
+    let lower = title.to_lowercase();
+    let words:Vec<&str> = lower.split(|c:char| !c.is_ascii_alphanumeric())
+        .filter(|w| !w.is_empty()).collect();
+    if words.is_empty() { "lsys".to_string() } else { words.join("_") }
+}
+
+/*
+A standalone svg for each order of each LSys, named from its title and
+order, in dir.  Orders too large to draw are left out, and returned.
+*/
+fn cli_figures(lsysv:&[LSys], dir:&str) -> Result<Skipped,LSysError> {
+    let io = |path:&str, why| LSysError::Io(path.to_string(), why);
+    std::fs::create_dir_all(dir).map_err(|why| io(dir, why))?;
+    let mut stems:Vec<String> = vec!();
+    let mut skipped = vec!();
+    for lsys in lsysv {
+        // titles which differ only in punctuation still get a file each
+        let mut stem = file_stem(&lsys.title);
+        if stems.contains(&stem) {
+            stem = format!("{stem}_{}", stems.len() + 1);
+        }
+        stems.push(stem.clone());
+        for order in lsys_orders(lsys, &layout_boxes_make())? {
+            let svg = match lsys_svg_figure(lsys, order) {
+                Err(why @ (LSysError::OrderTooHigh { .. }
+                          | LSysError::TooManySegments { .. }
+                          | LSysError::TooManyModules { .. })) => {
+                    skipped.push((lsys.title.clone(), why));
+                    continue;
+                }
+                r => r?,
+            };
+            let path = std::path::Path::new(dir).join(format!("{stem}_{order}.svg"));
+            let path = path.to_string_lossy();
+            std::fs::write(path.as_ref(), svg).map_err(|why| io(&path, why))?;
+        }
+    }
+    Ok(skipped)
+}
+
+/// Write lsysv, as chosen by cli_lsys, as opts says, returning what was not drawn
+pub fn cli_run(opts:&Options, lsysv:&[LSys]) -> Result<Skipped,LSysError> {
+    if lsysv.is_empty() {
+        return Err(LSysError::Usage("No LSys selected".to_string()));
+    }
+    if let Some(dir) = &opts.figures {
+        return cli_figures(lsysv, dir);
+    }
+    let many = matches!(opts.format, Format::Html | Format::Ps | Format::Pdf | Format::Lsys);
+    if !many && lsysv.len() > 1 {
+        return Err(LSysError::Usage(format!(
+            "Format '{}' holds one LSys, but {} are selected",
+            format_ext(opts.format), lsysv.len())));
+    }
+
+    let default = format!("lsys_examples.{}", format_ext(opts.format));
+    let path = opts.output.as_deref().unwrap_or(&default);
+    if path == "-" {
+        return cli_write(opts, lsysv, Box::new(std::io::stdout()), "stdout");
+    }
+    // written beside path first, so that a failure leaves any file there as it was
+    let part = format!("{path}.part");
+    let io = |why| LSysError::Io(path.to_string(), why);
+    let out = Box::new(File::create(&part).map_err(io)?);
+    match cli_write(opts, lsysv, out, path) {
+        Ok(skipped) => {
+            std::fs::rename(&part, path).map_err(io)?;
+            Ok(skipped)
+        }
+        Err(why) => {
+            let _ = std::fs::remove_file(&part);
+            Err(why)
+        }
+    }
+}
+
+// everything but the choice of file, out is closed on return
+fn cli_write(opts:&Options, lsysv:&[LSys], out:Box<dyn Write>, name:&str) -> Result<Skipped,LSysError> {
+    let draw = if opts.figure { lsys_draw_figure } else { lsys_draw_page };
+    match opts.format {
+        Format::Html | Format::Ps | Format::Pdf => {
+            let ds = &mut match opts.format {
+                Format::Ps  => doc_new_ps(),
+                Format::Pdf => doc_new_pdf(),
+                _           => doc_new(),
+            };
+            doc(ds, DocAct::DocOpenWriterTitle(out, name, "Lindenmayer System Examples"))?;
+            lsys_draw_pages(lsysv, ds, draw)
+        }
+        Format::Svg | Format::Eps | Format::Png => {
+            let lsys = &lsysv[0];
+            let ds = &mut match opts.format {
+                Format::Eps => doc_new_eps(),
+                Format::Png => doc_new_png(opts.png.clone()),
+                _           => doc_new_svg(),
+            };
+            doc(ds, DocAct::DocOpenWriterTitle(out, name, &lsys.title))?;
+            doc(ds, DocAct::PageStartComment(&lsys.title))?;
+            let skipped = draw(lsys, ds)?;
+            doc(ds, DocAct::PageEnd)?;
+            doc(ds, DocAct::DocClose)?;
+            Ok(skipped)
+        }
+        Format::Lsys => {
+            let texts:Vec<String> = lsysv.iter().map(text::lsys_to_text).collect();
+            let mut out = out;
+            out.write_all(texts.join("\n").as_bytes())
+                .and_then(|_| out.flush())
+                .map_err(|why| LSysError::Io(name.to_string(), why))?;
+            Ok(vec!())
+        }
+        Format::Obj | Format::Stl => {
+            let lsys = &lsysv[0];
+            let lines = mesh::lsys_polylines_3d(lsys, lsys_figure_order(lsys)?)?;
+            let tubes = || mesh::mesh_tubes(&lines, TUBE_RADIUS, TUBE_SIDES);
+            let bytes = match (opts.format, opts.tubes, opts.ascii) {
+                (Format::Obj, false, _) => mesh::obj_from_polylines(&lsys.title, &lines).into_bytes(),
+                (Format::Obj, true, _)  => mesh::obj_from_mesh(&lsys.title, &tubes()).into_bytes(),
+                (_, _, true)            => mesh::stl_ascii_from_mesh(&lsys.title, &tubes()).into_bytes(),
+                (_, _, false)           => mesh::stl_binary_from_mesh(&lsys.title, &tubes()),
+            };
+            let mut out = out;
+            out.write_all(&bytes)
+                .and_then(|_| out.flush())
+                .map_err(|why| LSysError::Io(name.to_string(), why))?;
+            Ok(vec!())
+        }
+        Format::Gcode | Format::Hpgl => {
+            let lsys = &lsysv[0];
+            let paths = plot::lsys_plot_paths(lsys, lsys_figure_order(lsys)?, &opts.plot)?;
+            let text = if opts.format == Format::Gcode {
+                plot::gcode_from_paths(&lsys.title, &paths, &opts.plot)
+            }
+            else {
+                plot::hpgl_from_paths(&paths, &opts.plot)
+            };
+            let mut out = out;
+            out.write_all(text.as_bytes())
+                .and_then(|_| out.flush())
+                .map_err(|why| LSysError::Io(name.to_string(), why))?;
+            Ok(vec!())
+        }
+    }
+}
+
+static TUBE_RADIUS:f64  = 0.1;      // steps
+static TUBE_SIDES:usize = 6;
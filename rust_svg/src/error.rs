@@ -0,0 +1,85 @@
+use std::fmt;
+
+/*----------------------------------------------------------------------
+Errors
+
+Everything that can go wrong with one LSys, or with the document it is
+drawn into, is reported as an LSysError rather than a panic, so that a
+caller with many of them can report a bad one and go on to the next.
+
+The parsers of rules and expressions give their reasons as strings,
+which become Invalid.  Positions in Unbalanced count modules of the
+derived string, from 0, since that is what the turtle sees.
+*/
+
+/// Why an LSys, or a document of them, could not be drawn
+#[derive(Debug)]
+pub enum LSysError {
+    /// A "]" or "}" with nothing open
+    Unbalanced { bracket: char, pos: usize },
+    /// A module the turtle has no action for
+    UnknownSymbol(String),
+    /// No order for the named layout box
+    MissingOrder(String),
+    /// A layout box the page does not have
+    MissingBox(String),
+    /// An order above ORDER_MAX
+    OrderTooHigh { order: i32 },
+    /// An order which would draw more than SEGMENTS_MAX, at least segments
+    TooManySegments { order: i32, segments: u128 },
+    /// An order which would derive more than MODULES_MAX, at least modules
+    TooManyModules { order: i32, modules: u128 },
+    /// Reading or writing the named file
+    Io(String, std::io::Error),
+    /// Document actions out of order
+    Doc(String),
+    /// Command line options which can not be carried out
+    Usage(String),
+    /// Start, rules or tables which do not parse or check
+    Invalid(String),
+    /// Growth is only known for D0L systems
+    NotD0L(String),
+}
+
+impl fmt::Display for LSysError {
+    fn fmt(&self, f:&mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LSysError::Unbalanced { bracket, pos } =>
+                write!(f, "Unbalanced '{bracket}' at module {pos}"),
+            LSysError::UnknownSymbol(sym) =>
+                write!(f, "Unknown symbol '{sym}'"),
+            LSysError::MissingOrder(name) =>
+                write!(f, "No order for box '{name}'"),
+            LSysError::MissingBox(name) =>
+                write!(f, "No layout box '{name}'"),
+            LSysError::OrderTooHigh { order } =>
+                write!(f, "Order {order} is above {}, the highest drawn", crate::ORDER_MAX),
+            LSysError::TooManySegments { order, segments } =>
+                write!(f, "Order {order} has {segments} segments, too many to draw"),
+            LSysError::TooManyModules { order, modules } =>
+                write!(f, "Order {order} derives {modules} modules, too many to draw"),
+            LSysError::Io(path, why) =>
+                write!(f, "{path}: {why}"),
+            LSysError::Doc(why) | LSysError::Usage(why) | LSysError::Invalid(why) =>
+                write!(f, "{why}"),
+            LSysError::NotD0L(why) =>
+                write!(f, "Not D0L: {why}"),
+        }
+    }
+}
+
+impl std::error::Error for LSysError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            LSysError::Io(_, why) => Some(why),
+            _ => None,
+        }
+    }
+}
+
+// reasons given by the parsers
+impl From<String> for LSysError {
+    fn from(why:String) -> Self {
+        LSysError::Invalid(why)
+    }
+}
@@ -0,0 +1,346 @@
+use std::collections::HashMap;
+
+/*----------------------------------------------------------------------
+Arithmetic expressions for parametric L-systems
+
+Expressions appear as module arguments, for example "F(l*0.5)", and as
+rule conditions, for example "t>2".  They are parsed once, when rules
+are prepared, and then evaluated for every module that is rewritten.
+
+Names are resolved when parsing.  A name is either one of the formal
+parameters of the rule, which become an index into the list of actual
+values, or one of the LSys constants, which are folded into numbers.
+Any other name is an error.  So evaluation itself can not fail.
+
+Comparisons and logical operators give 1.0 for true and 0.0 for false.
+Trigonometric functions work in degrees, like the turtle angle.
+
+Grammar, from lowest to highest precedence:
+
+    or      := and { "||" and }
+    and     := cmp { "&&" cmp }
+    cmp     := sum [ ("<" | "<=" | ">" | ">=" | "==" | "!=") sum ]
+    sum     := product { ("+" | "-") product }
+    product := unary { ("*" | "/" | "%") unary }
+    unary   := ("-" | "+" | "!") unary | power
+    power   := atom [ "^" unary ]
+    atom    := number | name | name "(" or { "," or } ")" | "(" or ")"
+*/
+
+/// A parsed expression, see expr_parse
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    /// A number or a constant
+    Num(f64),
+    /// A formal parameter, by index
+    Param(usize),
+    /// Unary "-"
+    Neg(Box<Expr>),
+    /// Unary "!"
+    Not(Box<Expr>),
+    /// Arithmetic, by its operator
+    Binary(char, Box<Expr>, Box<Expr>),
+    /// A comparison, "&&" or "||", one or zero
+    Compare(&'static str, Box<Expr>, Box<Expr>),
+    /// A function
+    Call(&'static str, Vec<Expr>),
+}
+
+// functions known to expressions, with their number of arguments
+static FUNCTIONS:[(&str,usize);13] = [
+    ("sin",1), ("cos",1), ("tan",1),
+    ("asin",1), ("acos",1), ("atan",1), ("atan2",2),
+    ("sqrt",1), ("abs",1), ("exp",1), ("log",1),
+    ("min",2), ("max",2),
+];
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Num(f64),
+    Name(String),
+    Op(&'static str),
+}
+
+static OPERATORS:[&str;19] = [
+    "||", "&&", "<=", ">=", "==", "!=",
+    "<", ">", "+", "-", "*", "/", "%", "^", "!", "(", ")", ",", "=",
+];
+
+fn expr_tokens(src:&str) -> Result<Vec<Token>,String> {
+    let chars:Vec<char> = src.chars().collect();
+    let mut tokens = vec!();
+    let mut i = 0;
+    'outer: while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        }
+        else if c.is_ascii_digit() || c == '.' {
+            let j = i;
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                i += 1;
+            }
+            // exponent
+            if i < chars.len() && (chars[i] == 'e' || chars[i] == 'E') {
+                let mut k = i + 1;
+                if k < chars.len() && (chars[k] == '+' || chars[k] == '-') {
+                    k += 1;
+                }
+                if k < chars.len() && chars[k].is_ascii_digit() {
+                    i = k;
+                    while i < chars.len() && chars[i].is_ascii_digit() {
+                        i += 1;
+                    }
+                }
+            }
+            let s:String = chars[j..i].iter().collect();
+            match s.parse::<f64>() {
+                Ok(v)  => tokens.push(Token::Num(v)),
+                Err(_) => return Err(format!("Bad number '{s}' in '{src}'")),
+            }
+        }
+        else if c.is_alphabetic() || c == '_' {
+            let j = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            tokens.push(Token::Name(chars[j..i].iter().collect()));
+        }
+        else {
+            for op in OPERATORS {
+                let n = op.len();
+                if i + n <= chars.len() && chars[i..i+n].iter().copied().eq(op.chars()) {
+                    tokens.push(Token::Op(op));
+                    i += n;
+                    continue 'outer;
+                }
+            }
+            return Err(format!("Unexpected '{c}' in '{src}'"));
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser<'a> {
+    src:    &'a str,
+    tokens: Vec<Token>,
+    pos:    usize,
+    names:  &'a [String],
+    consts: &'a HashMap<String,f64>,
+}
+
+fn parser_peek_op(p:&Parser, ops:&[&'static str]) -> Option<&'static str> {
+    match p.tokens.get(p.pos) {
+        Some(Token::Op(op)) if ops.contains(op) => Some(op),
+        _ => None,
+    }
+}
+
+fn parser_expect(p:&mut Parser, op:&str) -> Result<(),String> {
+    if parser_peek_op(p, &[")", ","]) == Some(op) {
+        p.pos += 1;
+        Ok(())
+    }
+    else {
+        Err(format!("Expected '{op}' in '{}'", p.src))
+    }
+}
+
+fn parse_or(p:&mut Parser) -> Result<Expr,String> {
+    let mut e = parse_and(p)?;
+    while parser_peek_op(p, &["||"]).is_some() {
+        p.pos += 1;
+        let r = parse_and(p)?;
+        e = Expr::Compare("||", Box::new(e), Box::new(r));
+    }
+    Ok(e)
+}
+
+fn parse_and(p:&mut Parser) -> Result<Expr,String> {
+    let mut e = parse_cmp(p)?;
+    while parser_peek_op(p, &["&&"]).is_some() {
+        p.pos += 1;
+        let r = parse_cmp(p)?;
+        e = Expr::Compare("&&", Box::new(e), Box::new(r));
+    }
+    Ok(e)
+}
+
+fn parse_cmp(p:&mut Parser) -> Result<Expr,String> {
+    let e = parse_sum(p)?;
+    // a single "=" is accepted as equality, as written in ABOP
+    match parser_peek_op(p, &["<", "<=", ">", ">=", "==", "!=", "="]) {
+        Some(op) => {
+            p.pos += 1;
+            let r = parse_sum(p)?;
+            let op = if op == "=" { "==" } else { op };
+            Ok(Expr::Compare(op, Box::new(e), Box::new(r)))
+        }
+        None => Ok(e),
+    }
+}
+
+fn parse_sum(p:&mut Parser) -> Result<Expr,String> {
+    let mut e = parse_product(p)?;
+    while let Some(op) = parser_peek_op(p, &["+", "-"]) {
+        p.pos += 1;
+        let r = parse_product(p)?;
+        e = Expr::Binary(op.chars().next().unwrap(), Box::new(e), Box::new(r));
+    }
+    Ok(e)
+}
+
+fn parse_product(p:&mut Parser) -> Result<Expr,String> {
+    let mut e = parse_unary(p)?;
+    while let Some(op) = parser_peek_op(p, &["*", "/", "%"]) {
+        p.pos += 1;
+        let r = parse_unary(p)?;
+        e = Expr::Binary(op.chars().next().unwrap(), Box::new(e), Box::new(r));
+    }
+    Ok(e)
+}
+
+fn parse_unary(p:&mut Parser) -> Result<Expr,String> {
+    match parser_peek_op(p, &["-", "+", "!"]) {
+        Some("-") => { p.pos += 1; Ok(Expr::Neg(Box::new(parse_unary(p)?))) }
+        Some("!") => { p.pos += 1; Ok(Expr::Not(Box::new(parse_unary(p)?))) }
+        Some(_)   => { p.pos += 1; parse_unary(p) }
+        None      => parse_power(p),
+    }
+}
+
+fn parse_power(p:&mut Parser) -> Result<Expr,String> {
+    let e = parse_atom(p)?;
+    if parser_peek_op(p, &["^"]).is_some() {
+        p.pos += 1;
+        let r = parse_unary(p)?;
+        return Ok(Expr::Binary('^', Box::new(e), Box::new(r)));
+    }
+    Ok(e)
+}
+
+fn parse_atom(p:&mut Parser) -> Result<Expr,String> {
+    let token = p.tokens.get(p.pos).cloned();
+    p.pos += 1;
+    match token {
+        Some(Token::Num(v)) => Ok(Expr::Num(v)),
+        Some(Token::Op("(")) => {
+            let e = parse_or(p)?;
+            parser_expect(p, ")")?;
+            Ok(e)
+        }
+        Some(Token::Name(name)) => {
+            if parser_peek_op(p, &["("]).is_some() {
+                p.pos += 1;
+                let mut args = vec![parse_or(p)?];
+                while parser_peek_op(p, &[","]).is_some() {
+                    p.pos += 1;
+                    args.push(parse_or(p)?);
+                }
+                parser_expect(p, ")")?;
+                return match FUNCTIONS.iter().find(|(f,_)| *f == name) {
+                    Some((f,n)) if *n == args.len() => Ok(Expr::Call(f, args)),
+                    Some((f,n)) => Err(format!(
+                        "Function '{f}' takes {n} argument(s) in '{}'", p.src)),
+                    None => Err(format!(
+                        "Unknown function '{name}' in '{}'", p.src)),
+                };
+            }
+            // formal parameters hide constants of the same name
+            if let Some(i) = p.names.iter().position(|n| *n == name) {
+                Ok(Expr::Param(i))
+            }
+            else if let Some(v) = p.consts.get(&name) {
+                Ok(Expr::Num(*v))
+            }
+            else {
+                Err(format!("Unknown name '{name}' in '{}'", p.src))
+            }
+        }
+        Some(Token::Op(op)) => Err(format!("Unexpected '{op}' in '{}'", p.src)),
+        None => Err(format!("Incomplete expression '{}'", p.src)),
+    }
+}
+
+/*
+Parse an expression.  The names are the formal parameters in scope,
+and the constants are those of the LSys.
+*/
+/// Parse src, with parameters named by names
+pub fn expr_parse(
+    src:&str,
+    names:&[String],
+    consts:&HashMap<String,f64>,
+) -> Result<Expr,String> {
+    let tokens = expr_tokens(src)?;
+    let mut p = Parser { src, tokens, pos:0, names, consts };
+    let e = parse_or(&mut p)?;
+    if p.pos != p.tokens.len() {
+        return Err(format!("Unexpected text at end of '{src}'"));
+    }
+    Ok(e)
+}
+
+/*
+Evaluate an expression given the actual values of the formal
+parameters, in the same order as the names given to expr_parse.
+*/
+/// The value of e, zero or one for comparisons
+pub fn expr_eval(e:&Expr, values:&[f64]) -> f64 {
+    let truth = |b:bool| if b { 1.0 } else { 0.0 };
+    match e {
+        Expr::Num(v)   => *v,
+        Expr::Param(i) => values[*i],
+        Expr::Neg(a)   => -expr_eval(a, values),
+        Expr::Not(a)   => truth(expr_eval(a, values) == 0.0),
+        Expr::Binary(op,a,b) => {
+            let (a,b) = (expr_eval(a, values), expr_eval(b, values));
+            match op {
+                '+' => a + b,
+                '-' => a - b,
+                '*' => a * b,
+                '/' => a / b,
+                '%' => a % b,
+                _   => a.powf(b),
+            }
+        }
+        Expr::Compare(op,a,b) => {
+            let a = expr_eval(a, values);
+            // short circuit
+            match *op {
+                "&&" if a == 0.0 => return 0.0,
+                "||" if a != 0.0 => return 1.0,
+                _ => (),
+            }
+            let b = expr_eval(b, values);
+            truth(match *op {
+                "<"  => a <  b,
+                "<=" => a <= b,
+                ">"  => a >  b,
+                ">=" => a >= b,
+                "==" => a == b,
+                "!=" => a != b,
+                _    => b != 0.0,    // && and || once short circuit fails
+            })
+        }
+        Expr::Call(f,args) => {
+            let a = expr_eval(&args[0], values);
+            let b = || expr_eval(&args[1], values);
+            match *f {
+                "sin"   => a.to_radians().sin(),
+                "cos"   => a.to_radians().cos(),
+                "tan"   => a.to_radians().tan(),
+                "asin"  => a.asin().to_degrees(),
+                "acos"  => a.acos().to_degrees(),
+                "atan"  => a.atan().to_degrees(),
+                "atan2" => a.atan2(b()).to_degrees(),
+                "sqrt"  => a.sqrt(),
+                "abs"   => a.abs(),
+                "exp"   => a.exp(),
+                "log"   => a.ln(),
+                "min"   => a.min(b()),
+                _       => a.max(b()),
+            }
+        }
+    }
+}
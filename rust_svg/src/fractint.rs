@@ -0,0 +1,294 @@
+use super::*;
+
+/*----------------------------------------------------------------------
+Fractint ".l" files
+
+Fractint keeps L-systems in files of entries like this:
+
+    Dragon {        ; comments start with ";"
+      Angle 8
+      Axiom FX
+      X=X+YF+
+      Y=-FX-Y
+    }
+
+"Angle n" divides the circle into n parts, so the angle of the LSys is
+360/n degrees.  Each rule has a single character on its left.  Case
+does not matter, and spaces within strings are ignored.
+
+Fractint commands are turned into those of an LSys:
+
+    F D     draw forward, D as one of the draws of the LSys
+    G M     move forward, G becoming "g" and M one of the moves
+    + - [ ] the same
+    |       turn around, or for an odd number of parts, by the
+            largest multiple of the angle below 180 degrees
+    \nn /nn turn left or right by nn degrees, as "+(nn)" and "-(nn)"
+    @nn     multiply the line length by nn, which may be preceded by
+            "I" for the inverse and "Q" for the square root, as ">(x)"
+    Cnn     use color nn, as "'(nn)", an index into the color map
+
+Text after the "}" closing an entry is read as the start of the next,
+and any which does not start one is ignored with a warning, as is text
+between entries.  "<nn" and ">nn", which step the color by nn, are
+left out with a warning.  "!", which swaps the meaning of the turns, has no equivalent,
+so an entry using it is skipped with an error.
+
+Other characters only name symbols.  Those which would be actions of
+an LSys, or break up its modules, are renamed to lower case letters,
+consistently within an entry, so that the derivation is the same.
+*/
+
+// lower case letters free for renamed symbols, "f" and "g" are actions
+static FRACTINT_FREE:&str = "abcehijklmnopqrstuvwxyz";
+
+// a string of the entry, with where it was found
+struct FractintString {
+    line:   usize,
+    column: usize,
+    text:   String,
+}
+
+struct FractintEntry {
+    name:   String,
+    line:   usize,
+    parts:  Option<u32>,                    // Angle
+    axiom:  Option<FractintString>,
+    rules:  Vec<(char,FractintString)>,
+    failed: bool,
+}
+
+// a number following a command, as text, taken from chars
+fn fractint_number(chars:&[char], i:&mut usize) -> Option<f64> {
+    let j = *i;
+    while chars.get(*i).is_some_and(|c| c.is_ascii_digit() || *c == '.') {
+        *i += 1;
+    }
+    chars[j..*i].iter().collect::<String>().parse().ok()
+}
+
+// rename a plain Fractint symbol, if it would mean something else here
+fn fractint_symbol(c:char, renamed:&mut Vec<char>) -> String {
+    if c.is_ascii_uppercase() || c.is_ascii_digit() {
+        return c.to_string();
+    }
+    let k = match renamed.iter().position(|&r| r == c) {
+        Some(k) => k,
+        None => {
+            renamed.push(c);
+            renamed.len() - 1
+        }
+    };
+    FRACTINT_FREE.chars().nth(k % FRACTINT_FREE.len()).map_or(c.to_string(), String::from)
+}
+
+/*
+One Fractint string in the syntax of an LSys.  Warnings are added to
+notes, an error is returned.
+*/
+fn fractint_translate(
+    s:&FractintString,
+    parts:u32,
+    renamed:&mut Vec<char>,
+    notes:&mut Vec<LoadNote>,
+) -> Result<String,String> {
+    let chars:Vec<char> = s.text.to_uppercase().chars().filter(|c| !c.is_whitespace()).collect();
+    let mut out = String::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        i += 1;
+        match c {
+            'F' | 'D' | 'M' | '+' | '-' | '[' | ']' => out.push(c),
+            'G' => out.push('g'),
+            '|' if parts.is_multiple_of(2) => out.push('|'),
+            '|' => out.push_str(&format!("+({})", (parts/2) as f64 * 360.0 / parts as f64)),
+            '\\' | '/' => {
+                let nn = fractint_number(&chars, &mut i)
+                    .ok_or_else(|| format!("Expected degrees after '{c}' in '{}'", s.text))?;
+                out.push_str(&format!("{}({nn})", if c == '\\' { '+' } else { '-' }));
+            }
+            '@' => {
+                let (mut inverse, mut root) = (false, false);
+                while let Some(&m) = chars.get(i).filter(|&&m| m == 'I' || m == 'Q') {
+                    inverse |= m == 'I';
+                    root |= m == 'Q';
+                    i += 1;
+                }
+                let mut x = fractint_number(&chars, &mut i)
+                    .ok_or_else(|| format!("Expected a number after '@' in '{}'", s.text))?;
+                if root {
+                    x = x.sqrt();
+                }
+                if inverse {
+                    x = 1.0 / x;
+                }
+                out.push_str(&format!(">({x})"));
+            }
+            'C' => {
+                let nn = fractint_number(&chars, &mut i)
+                    .ok_or_else(|| format!("Expected a color after 'C' in '{}'", s.text))?;
+                out.push_str(&format!("'({nn})"));
+            }
+            '<' | '>' => {
+                fractint_number(&chars, &mut i);
+                notes.push(LoadNote {
+                    line: s.line,
+                    column: s.column,
+                    error: false,
+                    text: format!("Color step '{c}' left out of '{}'", s.text),
+                    source: String::new(),
+                });
+            }
+            '!' => return Err(format!("'!' swaps turns, which is not supported, in '{}'", s.text)),
+            _ => out.push_str(&fractint_symbol(c, renamed)),
+        }
+    }
+    Ok(out)
+}
+
+fn fractint_note(notes:&mut Vec<LoadNote>, line:usize, column:usize, text:String) {
+    notes.push(LoadNote { line, column, error: true, text, source: String::new() });
+}
+
+// the LSys of a complete entry, or a note of why not
+fn fractint_finish(e:FractintEntry, lsysv:&mut Vec<LSys>, notes:&mut Vec<LoadNote>) {
+    if e.failed {
+        return;
+    }
+    let (Some(parts), Some(axiom)) = (e.parts.filter(|&n| n > 0), &e.axiom) else {
+        let text = format!("'{}' needs an Angle above 0 and an Axiom", e.name);
+        return fractint_note(notes, e.line, 1, text);
+    };
+    let mut renamed = vec!();
+    let mut lsys = LSys {
+        title: e.name.clone(),
+        angle: 360.0 / parts as f64,
+        ..Default::default()
+    };
+    let mut strings = vec![(None, axiom)];
+    strings.extend(e.rules.iter().map(|(lhs,s)| (Some(*lhs), s)));
+    for (lhs,s) in strings {
+        let text = match fractint_translate(s, parts, &mut renamed, notes) {
+            Ok(text) => text,
+            Err(why) => return fractint_note(notes, s.line, s.column, why),
+        };
+        let Some(lhs) = lhs else {
+            lsys.start = text;
+            continue;
+        };
+        let lhs = FractintString { text: lhs.to_string(), ..*s };
+        let lhs = fractint_translate(&lhs, parts, &mut renamed, notes)
+            .unwrap_or_else(|_| lhs.text.clone());
+        if lsys.rules.contains_key(&lhs) {
+            let text = format!("Second rule for '{lhs}' left out");
+            notes.push(LoadNote { line: s.line, column: s.column, error: false, text,
+                source: String::new() });
+            continue;
+        }
+        lsys.rules.insert(lhs, text.as_str().into());
+    }
+    for (c,field) in [('D', &mut lsys.draws), ('M', &mut lsys.moves)] {
+        let used = lsys.start.contains(c) || lsys.rules.iter()
+            .any(|(k,v)| k.contains(c) || matches!(v, Successor::Basic(s) if s.contains(c)));
+        if used {
+            field.push(c);
+        }
+    }
+    match lsys_check(&lsys) {
+        Ok(()) => lsysv.push(lsys),
+        Err(why) => fractint_note(notes, e.line, 1, format!("'{}': {why}", e.name)),
+    }
+}
+
+// one line within an entry, without its comment
+fn fractint_line(e:&mut FractintEntry, line:&str, n:usize, column:usize, notes:&mut Vec<LoadNote>) {
+    let line = line.trim();
+    if line.is_empty() || e.failed {
+        return;
+    }
+    let (word,rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+    let string = |text:&str| FractintString { line: n, column, text: text.trim().to_string() };
+    if word.eq_ignore_ascii_case("angle") {
+        match rest.trim().parse() {
+            Ok(parts) => e.parts = Some(parts),
+            Err(_) => {
+                fractint_note(notes, n, column, format!("Bad Angle '{}'", rest.trim()));
+                e.failed = true;
+            }
+        }
+    }
+    else if word.eq_ignore_ascii_case("axiom") {
+        e.axiom = Some(string(rest));
+    }
+    else if let Some((lhs,rhs)) = line.split_once('=').filter(|(l,_)| l.trim().chars().count() == 1) {
+        let lhs = lhs.trim().chars().next().unwrap_or(' ').to_ascii_uppercase();
+        e.rules.push((lhs, string(rhs)));
+    }
+    else {
+        fractint_note(notes, n, column, format!("Expected Angle, Axiom or a rule, not '{line}'"));
+        e.failed = true;
+    }
+}
+
+/// The entries of a Fractint file, and notes on what was skipped
+pub fn lsys_fractint_parse(src:&str) -> (Vec<LSys>,Vec<LoadNote>) {
+    let mut lsysv = vec!();
+    let mut notes = vec!();
+    let mut entry:Option<FractintEntry> = None;
+    for (i,raw) in src.lines().enumerate() {
+        let n = i + 1;
+        let mut line = raw.split(';').next().unwrap_or("");
+        let mut column = 1;
+        // an entry may end and another start on the same line
+        loop {
+            if entry.is_none() {
+                let skip = line.chars().take_while(|c| c.is_whitespace()).count();
+                let text = line.trim();
+                if text.is_empty() {
+                    break;
+                }
+                let Some((name,rest)) = line.split_once('{') else {
+                    notes.push(LoadNote { line: n, column: column + skip, error: false,
+                        text: format!("Ignored text '{text}'"), source: String::new() });
+                    break;
+                };
+                entry = Some(FractintEntry {
+                    name: name.trim().to_string(),
+                    line: n,
+                    parts: None,
+                    axiom: None,
+                    rules: vec!(),
+                    failed: false,
+                });
+                column += name.chars().count() + 1;
+                line = rest;
+            }
+            let Some(e) = entry.as_mut() else { break };
+            let skip = line.chars().take_while(|c| c.is_whitespace()).count();
+            match line.split_once('}') {
+                None => {
+                    fractint_line(e, line, n, column + skip, &mut notes);
+                    break;
+                }
+                Some((body,rest)) => {
+                    fractint_line(e, body, n, column + skip, &mut notes);
+                    if let Some(e) = entry.take() {
+                        fractint_finish(e, &mut lsysv, &mut notes);
+                    }
+                    column += body.chars().count() + 1;
+                    line = rest;
+                }
+            }
+        }
+    }
+    if let Some(e) = entry {
+        fractint_note(&mut notes, e.line, 1, format!("'{}' has no closing '}}'", e.name));
+    }
+    (lsysv, notes)
+}
+
+/// Load lsys from a Fractint file, with problems noted against the name of the source
+pub fn lsys_from_fractint(name:&str, src:&str) -> (Vec<LSys>,Vec<LoadNote>) {
+    load_named(name, lsys_fractint_parse(src))
+}
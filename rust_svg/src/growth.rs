@@ -0,0 +1,342 @@
+use super::*;
+
+/*----------------------------------------------------------------------
+Growth of D0L systems
+
+When every symbol has a single rule, with no context, no condition and
+no random choice, the number of each symbol after a step depends only
+on the numbers before it (ABOP section 1.2, and "growth functions" in
+the literature).  Row "a" of the growth matrix counts the symbols in
+the successor of "a", so that
+
+    counts(n+1) = counts(n) * M
+
+which gives the counts at any order without expanding the string.
+Parameters do not change the symbols produced, so parametric rules are
+fine as long as they have no conditions, and each symbol has the same
+number of parameters wherever it is, so that its rule always matches.
+With tables each step uses the matrix of its own table, and only the
+tables the schedule reaches need be D0L.
+
+The dominant eigenvalue of M is the growth rate, the factor by which
+the string grows at each step in the long run.  The number of line
+segments drawn follows from the counts and from what each symbol draws
+once post rules have been applied.
+
+Counts are exact, in u128, and None when they overflow even that.
+*/
+
+// sparse rows, each a list of (symbol index, count)
+type GrowthMatrix = Vec<Vec<(usize,u128)>>;
+
+/// The growth matrices of a D0L system, see growth_new
+pub struct Growth {
+    /// The alphabet, sorted
+    pub symbols: Vec<Sym>,
+    matrices: HashMap<String,GrowthMatrix>,   // for each table used
+    start:    Vec<u128>,            // counts in the start rule
+    drawn:    Vec<u128>,            // segments drawn by each symbol
+}
+
+// the number of parameters of sym, which must be the same wherever it is
+// used, or a rule might not match a module the matrix counts it rewriting
+fn growth_arity(arity:&mut HashMap<Sym,usize>, sym:&Sym, n:usize) -> Result<(),LSysError> {
+    match arity.insert(sym.clone(), n) {
+        Some(m) if m != n => Err(LSysError::NotD0L(format!(
+            "Symbol '{sym}' has {m} parameter(s) in one place and {n} in another"))),
+        _ => Ok(()),
+    }
+}
+
+// successor symbols of each symbol with a rule
+fn growth_successors(lsys:&LSys, rules:&Rules, arity:&mut HashMap<Sym,usize>)
+    -> Result<HashMap<Sym,Vec<Sym>>,LSysError>
+{
+    let mut out = HashMap::new();
+    for (sym,prods) in rules_productions(lsys, rules)? {
+        let p = &prods[0];
+        if prods.len() > 1 {
+            return Err(LSysError::NotD0L(format!("Symbol '{sym}' has more than one rule")));
+        }
+        if !p.left.is_empty() || !p.right.is_empty() {
+            return Err(LSysError::NotD0L(format!("Rule for '{sym}' has context")));
+        }
+        if p.cond.is_some() {
+            return Err(LSysError::NotD0L(format!("Rule for '{sym}' has a condition")));
+        }
+        if p.stochastic {
+            return Err(LSysError::NotD0L(format!("Rule for '{sym}' is stochastic")));
+        }
+        growth_arity(arity, &sym, p.pred.formals.len())?;
+        for t in &p.alts[0].1 {
+            growth_arity(arity, &t.sym, t.args.len())?;
+        }
+        let succ = p.alts[0].1.iter().map(|t| t.sym.clone()).collect();
+        out.insert(sym, succ);
+    }
+    Ok(out)
+}
+
+/// The growth matrices of lsys, Err NotD0L when it is not D0L
+pub fn growth_new(lsys:&LSys) -> Result<Growth,LSysError> {
+    let mut arity = HashMap::new();
+    let mut start = vec!();
+    for m in modules_parse(lsys, &lsys.start)? {
+        growth_arity(&mut arity, &m.sym, m.params.len())?;
+        start.push(m.sym);
+    }
+    let post = growth_successors(lsys, &lsys.post_rules, &mut arity)?;
+    let mut tables = HashMap::new();
+    for name in lsys_tables_used(lsys) {
+        let Some(rules) = lsys_table(lsys, name) else {
+            return Err(LSysError::Invalid(format!("Schedule refers to unknown table '{name}'")));
+        };
+        let succs = growth_successors(lsys, rules, &mut arity).map_err(|why| match why {
+            LSysError::NotD0L(why) => LSysError::NotD0L(format!("Table '{name}': {why}")),
+            why => why,
+        })?;
+        tables.insert(name.to_string(), succs);
+    }
+
+    // every symbol that can appear
+    let mut symbols:Vec<Sym> = start.clone();
+    for succs in tables.values() {
+        for (sym,succ) in succs {
+            symbols.push(sym.clone());
+            symbols.extend(succ.iter().cloned());
+        }
+    }
+    symbols.sort();
+    symbols.dedup();
+    let index = |c:&Sym| symbols.binary_search(c).unwrap();
+
+    let count = |syms:&[Sym]| {
+        let mut v = vec![0u128; symbols.len()];
+        for c in syms {
+            v[index(c)] += 1;
+        }
+        v
+    };
+    let matrices = tables.iter()
+        .map(|(name,succs)| {
+            let rows = symbols.iter()
+                .map(|c| match succs.get(c) {
+                    Some(succ) => count(succ).into_iter().enumerate()
+                        .filter(|(_,n)| *n > 0)
+                        .collect(),
+                    // no rule, so the symbol stays as it is
+                    None => vec![(index(c),1)],
+                })
+                .collect();
+            (name.clone(),rows)
+        })
+        .collect();
+    let acts = lsys_actions(lsys);
+    let drawn = symbols.iter()
+        .map(|c| match post.get(c) {
+            Some(succ) => succ.iter().filter(|s| actions_is_draw(&acts, s)).count() as u128,
+            None       => actions_is_draw(&acts, c) as u128,
+        })
+        .collect();
+    Ok(Growth { start: count(&start), symbols, matrices, drawn })
+}
+
+/// Counts of each symbol, in the order of growth.symbols
+pub fn growth_counts(lsys:&LSys, growth:&Growth, order:i32) -> Option<Vec<u128>> {
+    let mut counts = growth.start.clone();
+    for step in 0..order {
+        let m = &growth.matrices[lsys_table_at(lsys, step)];
+        let mut next = vec![0u128; counts.len()];
+        for (a,row) in m.iter().enumerate() {
+            if counts[a] == 0 {
+                continue;
+            }
+            for &(b,n) in row {
+                next[b] = next[b].checked_add(counts[a].checked_mul(n)?)?;
+            }
+        }
+        counts = next;
+    }
+    Some(counts)
+}
+
+/// Segments drawn, after post rules and minimizing
+pub fn growth_segments(lsys:&LSys, growth:&Growth, order:i32) -> Option<u128> {
+    let counts = growth_counts(lsys, growth, order)?;
+    let mut total:u128 = 0;
+    for (c,d) in counts.iter().zip(&growth.drawn) {
+        total = total.checked_add(c.checked_mul(*d)?)?;
+    }
+    Some(total)
+}
+
+/*
+The dominant eigenvalue, for the table used once the schedule has run
+out, found by repeating steps from the start rule.  The rate is the
+mean growth of the last few steps, so that it also settles for
+matrices where the string alternates between shapes.  Only symbols
+which can be reached from the start rule count.
+*/
+/// How many times longer the string gets at each step
+pub fn growth_rate(lsys:&LSys, growth:&Growth) -> f64 {
+    let name = lsys.schedule.last().map_or("rules", |(name,_)| name.as_str());
+    let m = &growth.matrices[name];
+    let mut v:Vec<f64> = growth.start.iter().map(|&n| n as f64).collect();
+    let mut log_sum = 0.0;
+    for i in 0..GROWTH_STEPS {
+        let mut next = vec![0.0; v.len()];
+        for (a,row) in m.iter().enumerate() {
+            for &(b,n) in row {
+                next[b] += v[a] * n as f64;
+            }
+        }
+        let total:f64 = next.iter().sum();
+        if total == 0.0 {
+            return 0.0;
+        }
+        if i >= GROWTH_STEPS - GROWTH_MEAN {
+            log_sum += total.ln();
+        }
+        // keep the total at one, so the new total is the growth
+        v = next.iter().map(|x| x / total).collect();
+    }
+    (log_sum / GROWTH_MEAN as f64).exp()
+}
+
+static GROWTH_STEPS:usize = 1000;       // steps to settle
+static GROWTH_MEAN:usize  = 60;         // steps averaged, for periods up to 60
+
+/*
+Segments drawn at an order, saturating when they will not even fit in
+u128.  None when the LSys is not D0L, so can not be predicted.
+*/
+/// Segments drawn at order, without deriving it
+pub fn lsys_segments_predict(lsys:&LSys, order:i32) -> Option<u128> {
+    let growth = growth_new(lsys).ok()?;
+    Some(growth_segments(lsys, &growth, order).unwrap_or(u128::MAX))
+}
+
+/*----------------------------------------------------------------------
+Random access
+
+A window of the string derived by D0L rules can be found without
+deriving the rest of it.  For each step, the length that every symbol
+grows into by the order is worked out from the growth matrices,
+working back from the order.  Then from the start rule only the
+modules that overlap the window are rewritten, and whole subtrees
+before it are skipped by their length.  So the time taken grows with
+the order and the size of the window, not with the length of the
+string.
+
+This is the string of lsys_apply_tables, before post rules.  Lengths
+saturate rather than overflow, which still finds the right module as
+long as the window itself starts below u128::MAX.  Orders are at most
+ORDER_MAX, as for drawing.
+*/
+
+struct Index<'a> {
+    lsys:    &'a LSys,
+    order:   usize,
+    growth:  Growth,
+    lengths: Vec<Vec<u128>>,    // for each step, the length of each symbol
+    prods:   HashMap<&'a str,Productions>,
+    values:  Vec<f64>,
+}
+
+// index_slice goes down a level for each step, so the order is capped
+// before anything is allocated for it
+fn index_new(lsys:&LSys, order:i32) -> Result<Index<'_>,LSysError> {
+    if order > ORDER_MAX {
+        return Err(LSysError::OrderTooHigh { order });
+    }
+    let growth = growth_new(lsys)?;
+    let order = order.max(0) as usize;
+    let mut lengths = vec![vec![1u128; growth.symbols.len()]];
+    let mut prods = HashMap::new();
+    for step in (0..order).rev() {
+        let name = lsys_table_at(lsys, step as i32);
+        let next = lengths.last().unwrap();
+        let lens = growth.matrices[name].iter()
+            .map(|row| row.iter().fold(0u128, |sum,&(b,n)|
+                sum.saturating_add(n.saturating_mul(next[b]))))
+            .collect();
+        lengths.push(lens);
+        if !prods.contains_key(name) {
+            prods.insert(name, lsys_table_productions(lsys, name)?);
+        }
+    }
+    lengths.reverse();
+    Ok(Index { lsys, order, growth, lengths, prods, values: vec!() })
+}
+
+fn index_length(ix:&Index, level:usize, m:&Module) -> u128 {
+    let i = ix.growth.symbols.binary_search(&m.sym).unwrap();
+    ix.lengths[level][i]
+}
+
+// push modules of the window, skipping the given number first
+fn index_slice(
+    ix:&mut Index,
+    level:usize,
+    modules:&[Module],
+    skip:&mut u128,
+    count:usize,
+    out:&mut Vec<Module>,
+) -> Result<(),LSysError> {
+    for m in modules {
+        if out.len() == count {
+            break;
+        }
+        let len = index_length(ix, level, m);
+        if *skip >= len {
+            *skip -= len;
+            continue;
+        }
+        if level == ix.order {
+            out.push(m.clone());
+            continue;
+        }
+        let prods = &ix.prods[lsys_table_at(ix.lsys, level as i32)];
+        let values = &mut ix.values;
+        let succ = match prods.get(&m.sym) {
+            None => vec![m.clone()],
+            Some(v) => {
+                let p = &v[0];
+                if !production_match(p, std::slice::from_ref(m), 0, &[], values) {
+                    return Err(LSysError::Invalid(format!(
+                        "Module '{}' with {} parameter(s) does not match its rule",
+                        m.sym, m.params.len())));
+                }
+                let mut succ = vec!();
+                templates_eval(&p.alts[0].1, values, &mut succ);
+                succ
+            }
+        };
+        index_slice(ix, level + 1, &succ, skip, count, out)?;
+    }
+    Ok(())
+}
+
+/// Length of the string derived to an order, saturating
+pub fn lsys_derived_length(lsys:&LSys, order:i32) -> Result<u128,LSysError> {
+    let ix = index_new(lsys, order)?;
+    let start = modules_parse(lsys, &lsys.start)?;
+    Ok(start.iter().fold(0u128, |sum,m| sum.saturating_add(index_length(&ix, 0, m))))
+}
+
+/// Up to count modules, from position first of the string derived to an order
+pub fn lsys_derived_slice(lsys:&LSys, order:i32, first:u128, count:usize)
+    -> Result<Vec<Module>,LSysError>
+{
+    let mut ix = index_new(lsys, order)?;
+    let start = modules_parse(lsys, &lsys.start)?;
+    let mut skip = first;
+    let mut out = vec!();
+    index_slice(&mut ix, 0, &start, &mut skip, count, &mut out)?;
+    Ok(out)
+}
+
+/// The module at position k, None when past the end
+pub fn lsys_derived_at(lsys:&LSys, order:i32, k:u128) -> Result<Option<Module>,LSysError> {
+    Ok(lsys_derived_slice(lsys, order, k, 1)?.pop())
+}
@@ -0,0 +1,2833 @@
+//! Lindenmayer systems, derived and drawn as SVG.
+//!
+//! An [`LSys`] is usually read from json, with [`lsys_from_json`] or
+//! [`lsys_json_parse`], or from text with [`text::lsys_from_text`],
+//! which also check it with [`lsys_check`].  Then
+//!
+//! - [`lsys_apply_rules`] gives the whole derived string, and
+//!   [`lsys_derive`] gives it one module at a time,
+//! - [`turtle_run`] interprets modules as turtle moves in 3D, and
+//!   [`lsys_dacts`] as relative drawing actions in 2D,
+//! - [`lsys_draw_basic`] draws one order into a box, as SVG,
+//! - [`lsys_draw_page`] lays out a page with four orders, title and
+//!   rules, into a document made by [`doc_new`] and [`doc`],
+//! - [`lsys_draw_doc`] writes an HTML document with a page for each.
+//!
+//! Growth of D0L systems is in [`growth`], 3D output in [`mesh`],
+//! Fractint files in [`fractint`], and the command line of the binary
+//! in [`cli`].
+//! Everything that can fail returns an [`LSysError`].
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::Arc;
+use std::f64::consts::PI;
+use std::f64;
+use std::fs::OpenOptions;
+use std::io::Write;
+
+use serde::{Deserialize, Serialize};
+use indoc::indoc;
+
+mod expr;
+use expr::{Expr, expr_parse, expr_eval};
+
+mod error;
+pub use error::LSysError;
+
+/// 3D polylines, tubes, OBJ and STL
+pub mod mesh;
+/// Growth of D0L systems, without deriving them
+pub mod growth;
+/// Regexes to select titles with
+pub mod regex;
+/// The .lsys text format
+pub mod text;
+/// Fractint .l files
+pub mod fractint;
+/// Svg color names as rgb
+pub mod color;
+mod ps;
+mod pdf;
+mod zlib;
+/// Png rasters
+pub mod png;
+/// G-code and HP-GL for pen plotters
+pub mod plot;
+/// The command line
+pub mod cli;
+
+#[cfg(test)]
+mod test_main;
+
+/*----------------------------------------------------------------------
+HTML/SVG output document state management
+
+This collects page fragments and inserts various headers and footers.
+A document is HTML with an inline svg for each page, or made by
+doc_new_svg it is a single svg page on its own.  Made by doc_new_ps it
+is PostScript with a page for each, and by doc_new_eps a single page
+of encapsulated PostScript, see ps.rs.  Made by doc_new_pdf it is PDF,
+see pdf.rs.  Made by doc_new_png it is a single page of png, see png.rs,
+which is drawn rather than written, so its fragments hold nothing but
+comments.  Otherwise the fragments of a page must be in the language of
+the document, which for pdf is that of its content streams.
+*/
+
+/// What a document is written as
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DocKind {
+    /// Pages of figures, in html
+    Html,
+    /// One page, in svg
+    Svg,
+    /// PostScript
+    Ps,
+    /// One page, in encapsulated PostScript
+    Eps,
+    /// Pdf
+    Pdf,
+    /// One page, in png
+    Png,
+}
+
+/// Document actions
+pub enum DocAct<'a> {
+    /// Start new document, specify path to output file and title
+    DocOpenPathTitle(&'a str, &'a str),
+    /// Start new document, writing to a stream, with its name for errors and title
+    DocOpenWriterTitle(Box<dyn Write>, &'a str, &'a str),
+    /// Start a new page, and specify comment
+    PageStartComment(&'a str),
+    /// Add a data fragment to the page (content of data is not checked)
+    PageAddFragment(&'a str),
+    /// Add to the area drawn on the page, in pixels, which bounds eps and png
+    PageAddBBox(BBox),
+    /// Make an area of the page, in pixels, a link to a URI, for pdf
+    PageAddLink(BBox, &'a str),
+    /// Close out page and write to file
+    PageEnd,
+    /// Drop the page started last, so far as it has not been written
+    PageDiscard,
+    /// Close out document and write to file
+    DocClose
+}
+
+/// Document state, made by doc_new, doc_new_svg, doc_new_ps, doc_new_eps, doc_new_pdf or doc_new_png
+pub struct DocState {
+    kind    : DocKind,
+    indoc   : bool,             // inside a document
+    inpage  : bool,             // inside a page
+    page_no : usize,            // number of current page
+    frag_no : usize,            // number of current fragment in page
+    page_at : usize,            // length of buf when the page started
+    buf     : Vec<u8>,          // svg output buffer
+    path    : String,           // path of output file, for errors
+    title   : String,           // for headers written after the page
+    bbox    : Option<BBox>,     // area drawn on the page so far
+    links   : Vec<(BBox,String)>,       // links on the page, for pdf
+    written : usize,            // bytes written to file
+    objects : Vec<usize>,       // offset of each pdf object, by number
+    kids    : Vec<usize>,       // pdf page objects
+    png     : png::PngOptions,
+    raster  : Option<png::Raster>,      // png page being drawn
+    file    : Option<Box<dyn Write>>,   // where to write output
+}
+
+/// A new html document
+pub fn doc_new() -> DocState {
+    DocState {
+        kind    : DocKind::Html,
+        indoc   : false,
+        inpage  : false,
+        page_no : 0,
+        frag_no : 0,
+        page_at : 0,
+        buf     : vec!(),
+        path    : String::new(),
+        title   : String::new(),
+        bbox    : None,
+        links   : vec!(),
+        written : 0,
+        objects : vec!(),
+        kids    : vec!(),
+        png     : png::png_options_new(),
+        raster  : None,
+        file    : None,
+    }
+}
+
+/// A new svg document, one page
+pub fn doc_new_svg() -> DocState {
+    DocState { kind: DocKind::Svg, ..doc_new() }
+}
+
+/// A new PostScript document
+pub fn doc_new_ps() -> DocState {
+    DocState { kind: DocKind::Ps, ..doc_new() }
+}
+
+/// A new encapsulated PostScript document, one page
+pub fn doc_new_eps() -> DocState {
+    DocState { kind: DocKind::Eps, ..doc_new() }
+}
+
+/// A new pdf document
+pub fn doc_new_pdf() -> DocState {
+    DocState { kind: DocKind::Pdf, ..doc_new() }
+}
+
+/// A new png document, one page, drawn as opts says
+pub fn doc_new_png(opts:png::PngOptions) -> DocState {
+    DocState { kind: DocKind::Png, png: opts, ..doc_new() }
+}
+
+// the png page being drawn
+fn doc_raster(ds:&mut DocState) -> Result<&mut png::Raster,LSysError> {
+    ds.raster.as_mut().ok_or_else(|| LSysError::Doc("Drawing outside a page".to_string()))
+}
+
+// true when the document is PostScript, of either kind
+fn doc_is_ps(ds:&DocState) -> bool {
+    matches!(ds.kind, DocKind::Ps | DocKind::Eps)
+}
+
+// a comment line, in the language of the document
+fn doc_comment(ds:&DocState, text:&str) -> String {
+    if doc_is_ps(ds) || ds.kind == DocKind::Pdf {
+        format!("% {text}\n")
+    }
+    else {
+        format!("<!-- {} -->\n", xml_comment(text))
+    }
+}
+
+// text with the characters xml reserves escaped
+fn xml_escape(text:&str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+// text for inside an xml comment, which can not hold "--"
+fn xml_comment(text:&str) -> String {
+    let mut text = text.to_string();
+    while text.contains("--") {
+        text = text.replace("--", "-");
+    }
+    text
+}
+
+// an error unless the document is in the state an action needs
+fn doc_expect(ok:bool, why:&str) -> Result<(),LSysError> {
+    if ok { Ok(()) } else { Err(LSysError::Doc(why.to_string())) }
+}
+
+fn doc_write(ds:&mut DocState) -> Result<(),LSysError> {
+    let Some(file) = ds.file.as_mut() else {
+        return Err(LSysError::Doc("No file to write".to_string()));
+    };
+    file.write_all(&ds.buf)
+        .and_then(|_| file.flush())
+        .map_err(|why| LSysError::Io(ds.path.clone(), why))?;
+    ds.written += ds.buf.len();
+    ds.buf.clear();
+    Ok(())
+}
+
+/// Carry out one document action, Err when out of order or on IO failure
+pub fn doc(ds:& mut DocState, doc_act:DocAct) -> Result<(),LSysError> {
+    match doc_act {
+        DocAct::DocOpenPathTitle(path,title) => {
+            doc_expect(ds.file.is_none(), "Document already opened")?;
+            // open the file
+            let file = OpenOptions::new()
+                .write(true).create(true).truncate(true)
+                .open(path)
+                .map_err(|why| LSysError::Io(path.to_string(), why))?;
+            doc(ds, DocAct::DocOpenWriterTitle(Box::new(file), path, title))?;
+        }
+        DocAct::DocOpenWriterTitle(file,path,title) => {
+            // must be completely blank
+            doc_expect(!ds.indoc && !ds.inpage && ds.page_no == 0
+                && ds.frag_no == 0 && ds.buf.is_empty() && ds.file.is_none(),
+                "Document already opened")?;
+            ds.file = Some(file);
+            ds.path = path.to_string();
+            ds.title = title.to_string();
+            ds.indoc = true;
+            match ds.kind {
+                DocKind::Html => (),
+                // eps needs its bounding box first, so waits for the page
+                DocKind::Svg | DocKind::Eps | DocKind::Png => return Ok(()),
+                DocKind::Ps => {
+                    ds.buf.append(&mut ps::ps_doc_head(title, None, true).into_bytes());
+                    return Ok(());
+                }
+                DocKind::Pdf => {
+                    pdf::pdf_doc_head(ds);
+                    return Ok(());
+                }
+            }
+            // document header
+            let html_doc_head = format!( indoc! {r#"
+                <!DOCTYPE html>
+                <html lang="en">
+                <head>
+                    <meta charset="UTF-8">
+                    <title>{title}</title>
+                </head><body>
+                <h1>{title}</h1>
+
+                "#},
+                title = xml_escape(title),
+            );
+            ds.buf.append(&mut html_doc_head.into_bytes());
+        }
+        DocAct::PageStartComment(comment) => {
+            // check state
+            doc_expect(ds.indoc && !ds.inpage, "Page started outside a document, or in a page")?;
+            let single = matches!(ds.kind, DocKind::Svg | DocKind::Eps | DocKind::Png);
+            doc_expect(!single || ds.page_no == 0, "An svg, eps or png document has only one page")?;
+            ds.page_at = ds.buf.len();
+            ds.bbox = None;
+            ds.links.clear();
+            // emit page separator
+            if ds.kind == DocKind::Html && ds.page_no > 0 {
+                let html_page_sep = indoc! {r#"
+
+                <hr>
+
+                "#}.to_string();
+                ds.buf.append(&mut html_page_sep.into_bytes());
+            }
+            // emit page header
+            ds.page_no += 1;
+            ds.frag_no = 0;
+            ds.inpage = true;
+            if doc_is_ps(ds) {
+                ds.buf.append(&mut ps::ps_page_head(ds.page_no, comment).into_bytes());
+                return Ok(());
+            }
+            if ds.kind == DocKind::Pdf {
+                ds.buf.append(&mut pdf::pdf_page_head(ds.page_no, comment).into_bytes());
+                return Ok(());
+            }
+            if ds.kind == DocKind::Png {
+                ds.raster = Some(png::raster_new(&ds.png));
+                return Ok(());
+            }
+            // namespaces, which inline svg has from html
+            let ns = if ds.kind == DocKind::Svg { SVG_NAMESPACES } else { "" };
+            let svg_page_head = format!( indoc! {r#"
+                <!-- begin page {page_no}
+                     {comment} -->
+                <svg{ns}
+                    width="{page_width}"
+                    height="{page_height}"
+                >
+
+                "#},
+                page_no = ds.page_no,
+                comment = xml_comment(comment),
+                ns = ns,
+                page_width   = PAGE_WIDTH,
+                page_height  = PAGE_HEIGHT,
+            );
+            ds.buf.append(&mut svg_page_head.into_bytes());
+        }
+        DocAct::PageAddFragment(frag) => {
+            // check state
+            doc_expect(ds.inpage, "Fragment added outside a page")?;
+            // fragment header
+            ds.frag_no += 1;
+            if ds.kind == DocKind::Png {
+                return Ok(());
+            }
+            let text = format!("page {} fragment {}", ds.page_no, ds.frag_no);
+            let frag_head = format!("\n{}", doc_comment(ds, &text));
+            ds.buf.append(& mut frag_head.into_bytes());
+            // collect fragment
+            let mut frag:Vec<u8> = frag.as_bytes().to_vec();
+            ds.buf.append(&mut frag);
+        }
+        DocAct::PageAddBBox(bb) => {
+            doc_expect(ds.inpage, "Bounding box added outside a page")?;
+            ds.bbox = Some(match ds.bbox {
+                None => bb,
+                Some(b) => (b.0.min(bb.0), b.1.min(bb.1), b.2.max(bb.2), b.3.max(bb.3)),
+            });
+        }
+        DocAct::PageAddLink(bb,uri) => {
+            doc_expect(ds.inpage, "Link added outside a page")?;
+            ds.links.push((bb, uri.to_string()));
+        }
+        DocAct::PageEnd => {
+            // check state
+            doc_expect(ds.indoc && ds.inpage, "Page ended outside a page")?;
+            doc_expect(ds.frag_no > 0, "Page ended with nothing on it")?;
+            // page footer
+            ds.inpage = false;
+            if doc_is_ps(ds) {
+                ds.buf.append(&mut ps::ps_page_foot(ds.page_no).into_bytes());
+                if ds.kind == DocKind::Eps {
+                    let fonts = String::from_utf8_lossy(&ds.buf).contains("findfont");
+                    let bbox = ds.bbox.unwrap_or((0.0, 0.0, PAGE_WIDTH, PAGE_HEIGHT));
+                    let head = ps::ps_doc_head(&ds.title, Some(bbox), fonts);
+                    ds.buf.splice(0..0, head.into_bytes());
+                }
+                return doc_write(ds);
+            }
+            if ds.kind == DocKind::Pdf {
+                pdf::pdf_page_end(ds);
+                return doc_write(ds);
+            }
+            if let Some(raster) = ds.raster.take() {
+                let bbox = ds.bbox.unwrap_or((0.0, 0.0, PAGE_WIDTH, PAGE_HEIGHT));
+                ds.buf.append(&mut png::png_encode(&raster, &bbox));
+                return doc_write(ds);
+            }
+            let svg_page_foot = format!( indoc! {r#"
+
+                </svg>
+                <!-- end page {page_no} -->
+
+                "#},
+                page_no = ds.page_no,
+            );
+            ds.buf.append(&mut svg_page_foot.into_bytes());
+            // write page
+            doc_write(ds)?;
+        }
+        DocAct::PageDiscard => {
+            // check state
+            doc_expect(ds.indoc && ds.inpage, "Page discarded outside a page")?;
+            ds.buf.truncate(ds.page_at);
+            ds.raster = None;
+            ds.page_no -= 1;
+            ds.frag_no = 0;
+            ds.inpage = false;
+        }
+        DocAct::DocClose => {
+            // check state
+            doc_expect(ds.indoc && !ds.inpage && ds.buf.is_empty(),
+                "Document closed outside a document, or in a page")?;
+            doc_expect(ds.page_no > 0, "Document closed with no pages")?;
+            // doc footer
+            let doc_foot = match ds.kind {
+                DocKind::Html => indoc! {r#"
+                    </body></html>
+                    "#}.to_string(),
+                DocKind::Svg | DocKind::Png => String::new(),
+                DocKind::Ps  => ps::ps_doc_foot(Some(ds.page_no)),
+                DocKind::Eps => ps::ps_doc_foot(None),
+                DocKind::Pdf => {
+                    pdf::pdf_doc_foot(ds);
+                    String::new()
+                }
+            };
+            ds.buf.append(&mut doc_foot.into_bytes());
+            // write buf to file
+            doc_write(ds)?;
+            // Leave state with the file still set,
+            // so that any further DocAct will fail.
+            ds.indoc = false;
+            ds.frag_no = 0;
+        }
+    }
+    Ok(())
+}
+
+/*----------------------------------------------------------------------
+Page layout bounding boxes
+
+For convenience of page layout, define bounding boxes for
+the regions, "top", "a", "b", "left", "center", "right", "main",
+as diagrammed below.
+
+Note that the page origin for SVG is at top left.  This is different
+from that used by postscript which is at the bottom left.
+The orientation of y-axis for SVG is inverted
+
+    +-----------------0-----------------+
+    |                top                |
+    +------------1---+------------------+
+    |                |                  |
+    |       a        2        b         |
+    |                |                  |
+    +----------+-2---+-----+------------+
+    |          |           |            |
+    0 left     1  center   3   right    4
+    |          |           |            |
+    +----------+-----3-----+------------+
+    |                                   |
+    |                                   |
+    |                                   |
+    |                                   |
+    |                                   |
+    |                                   |
+    |                                   |
+    |               main                |
+    |                                   |
+    |                                   |
+    |                                   |
+    |                                   |
+    |                                   |
+    |                                   |
+    +---------------4-------------------+
+*/
+/// Bounding box, (x0,y0,x1,y1)
+pub type BBox = (f64,f64,f64,f64);
+/// Layout boxes by name
+pub type LayoutBoxes<'a> = HashMap<&'a str,BBox>;
+/// The layout boxes of a page, in pixels
+pub fn layout_boxes_make() -> LayoutBoxes<'static> {
+
+    // all box edges as fraction of page size
+    //             0     1     2     3     4
+    let xf = vec![0.05, 0.35, 0.35, 0.65, 0.95];
+    let yf = vec![0.03, 0.14, 0.20, 0.42, 0.97];
+
+    // scale to page size
+    let width:f64  = PAGE_WIDTH;
+    let x:Vec<f64> = xf.into_iter().map(|x| x * width).collect();
+    let height:f64 = PAGE_HEIGHT;
+    let y:Vec<f64> = yf.into_iter().map(|y| y * height).collect();
+
+    // make named bounding boxes
+    HashMap::from([
+        (  "main"   , (x[0],y[3],x[4],y[4]) ),
+        (  "left"   , (x[0],y[2],x[1],y[3]) ),
+        (  "center" , (x[1],y[2],x[3],y[3]) ),
+        (  "right"  , (x[3],y[2],x[4],y[3]) ),
+        (  "a"      , (x[0],y[1],x[2],y[2]) ),
+        (  "b"      , (x[2],y[1],x[4],y[2]) ),
+        (  "top"    , (x[0],y[0],x[4],y[1]) ),
+    ])
+}
+/// The named layout box, Err MissingBox if there is none
+pub fn layout_box<'a>(lb:&'a LayoutBoxes, name:&str) -> Result<&'a BBox,LSysError> {
+    lb.get(name).ok_or_else(|| LSysError::MissingBox(name.to_string()))
+}
+/// Outlines of layout boxes, as SVG
+pub fn layout_boxes_draw(boxes: &LayoutBoxes) -> String {
+    let mut svg = String::new();
+
+    // foreach box
+    for v in boxes.values() {
+        let s = format!( indoc! {r#"
+            <rect
+                x      = "{x0:.4}"
+                y      = "{y0:.4}"
+                rx     = "{box_radius}"
+                ry     = "{box_radius}"
+                width  = "{w:.4}"
+                height = "{h:.4}"
+                style  = "
+                    fill           :  none;
+                    stroke         :  black;
+                    stroke-width   :  {stroke_width};
+                "
+            />
+            "#},
+            x0=v.0,y0=v.1,w=v.2-v.0,h=v.3-v.1,
+            stroke_width = STROKE_WIDTH,
+            box_radius = BOX_RADIUS,
+        );
+        svg.push_str(&s);
+    }
+    svg
+}
+
+/*----------------------------------------------------------------------
+Lindenmayer System interpreter and display using SVG.
+
+An LSys is a set of rules for string substitution. There is a starting
+string and a set of rule strings.  Each character in a string is either the
+name of another rule, or a special action character.  Longer names,
+such as "Apex", may be declared in the list of symbols of the LSys.
+
+Any character may be followed by a list of numeric parameters in
+parenthesis, making it a module of a parametric L-system (ABOP chapter
+1.10), for example "F(1.5)" or "A(2,10)".  Rules then compute the
+parameters of new modules from those of the module being replaced.
+
+The special action characters are:
+F Move forward by line length drawing a line
+f Move forward by line length without drawing a line
+G Move forward drawing a line, but never record a polygon vertex
+g Move forward without drawing, and never record a polygon vertex
++ Turn left by turning angle
+- Turn right by turning angle
+| Reverse direction (ie: turn by 180 degrees)
+[ Push current drawing state onto stack
+] Pop current drawing state from the stack
+{ Start a polygon at the current position
+. Record the current position as a polygon vertex
+} Close the polygon and fill it
+' Use the next color of the color map
+! Multiply the line width by the width factor
+# Reset the line width to one
+> Multiply the line length by the length factor
+< Divide the line length by the length factor
+
+For "F" and "f" a parameter gives the line length, otherwise the
+current line length, which starts as one step.
+For "+" and "-" a parameter gives the turning angle in degrees,
+otherwise the angle of the LSys.
+
+For "'" a parameter gives the index into the color map, and for "!"
+and "#" it gives the new line width.  Width one is the normal stroke.
+The width factor of the LSys is 0.7 unless given, so that repeated "!"
+thins a trunk toward its tips without it ever vanishing.  This scales
+rather than decrements the width, as "!" was first meant to: starting
+from width one, a decrement of one left nothing to draw after a single
+"!", and any smaller fixed step still reaches zero after a few.
+For ">" and "<" a parameter is used in place of the length factor.
+
+While a polygon is open, "F" and "f" also record a vertex where they
+end, so that a leaf can be drawn as in ABOP, by "{-f+f+f-|-f+f+f}".
+Polygons may be nested, each "{" starting a new one until its "}".
+
+Many published L-systems draw with other symbols, such as "A" and "B".
+The LSys may list extra symbols which draw like "F", and extra symbols
+which move like "f", so these need no post rules.
+
+The drawing state consists of:
+- drawing direction, in three dimensions
+- drawing position
+- color, line width and line length
+
+Structured Vector Graphics (SVG) is generated to draw the LSys.
+This is a rewrite of previous version from python/postscript.
+*/
+
+/*----------------------------------------------------------------------
+The Lindenmayer System
+*/
+
+/// Symbols the turtle acts on
+pub static ACTIONS:&str = "FfGg+-[]|&^\\/${.}'!#><";
+
+/*
+The right hand side of a rule is either a single replacement string,
+or a list of weighted alternatives for a stochastic L-system (ABOP
+section 1.7).  In json the stochastic form is a list of [weight,string]
+pairs, for example:
+
+    "F" : [ [0.33, "F[+F]F[-F]F"], [0.33, "F[+F]F"], [0.34, "F[-F]F"] ]
+
+Weights are relative, they need not sum to one.
+*/
+/// The right hand side of a rule
+#[derive(Debug, Clone, PartialEq)]
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Successor {
+    /// A single replacement
+    Basic(String),
+    /// Weighted alternatives
+    Stochastic(Vec<(f64,String)>),
+}
+
+impl From<&str> for Successor {
+    fn from(s:&str) -> Successor {
+        Successor::Basic(s.to_string())
+    }
+}
+
+/*
+Rules are keyed by their left hand side.  This is usually a single
+symbol, but may also carry a left and/or right context for
+context-sensitive L-systems (ABOP section 1.8), written as:
+
+    "L < P > R"     P with L to its left and R to its right
+    "L < P"         P with L to its left
+    "P > R"         P with R to its right
+
+Spaces are ignored.  The left context is a plain string of symbols.
+The right context may contain brackets to match against branches,
+as in "S > G[H]M".
+
+So "<" and ">" in a left hand side always separate the contexts.  The
+turtle actions of the same names, which scale the line length, can
+not be rewritten or be part of a context, and a rule which tries is an
+error.
+
+For parametric L-systems each module of the left hand side names its
+formal parameters, and a condition may follow a ":", as in
+
+    "B(x) < A(t) : t > x"
+
+A module only matches one with the same symbol and the same number of
+parameters.  When several rules apply to the same symbol, the one with
+the most context is used, then one with a condition over one without.
+*/
+/// Rules by their left hand side
+pub type Rules = HashMap<String,Successor>;
+
+/// An L-system, as read from json
+#[derive(Debug, Default, Clone, PartialEq)]
+#[derive(Serialize, Deserialize)]
+pub struct LSys {
+    /// Name or description of the lsys
+    pub title: String,
+    /// List of places found on the web
+    pub refs:  Vec<String>,
+    /// Initial rule
+    pub start: String,
+    /// The angle step
+    pub angle: f64,
+    /// List of orders to be displayed, see lsys_orders
+    pub order: Vec<i32>,
+    /// Other rules referenced from start rule
+    pub rules: Rules,
+    /// Final rules applied only once
+    pub post_rules: Rules,
+    #[serde(default)]
+    /// Random seed for stochastic rules
+    pub seed: u64,
+    #[serde(default)]
+    /// Symbols skipped by context matching
+    pub ignore: String,
+    #[serde(default)]
+    /// Named values for rule expressions
+    pub consts: HashMap<String,f64>,
+    #[serde(default)]
+    /// Named rule tables
+    pub tables: HashMap<String,Rules>,
+    #[serde(default)]
+    /// Tables to use, and for how many steps
+    pub schedule: Vec<(String,i32)>,
+    #[serde(default)]
+    /// Color map, svg color names
+    pub colors: Vec<String>,
+    #[serde(default)]
+    /// Line length scale for ">" and "<", zero for none
+    pub length_factor: f64,
+    #[serde(default)]
+    /// Line width scale for "!", zero for WIDTH_FACTOR.  A scale rather
+    /// than a decrement, so that widths never reach zero
+    pub width_factor: f64,
+    #[serde(default)]
+    /// Extra symbols which draw like "F"
+    pub draws: String,
+    #[serde(default)]
+    /// Extra symbols which move like "f"
+    pub moves: String,
+    #[serde(default)]
+    /// Names of more than one character
+    pub symbols: Vec<String>,
+}
+
+/*----------------------------------------------------------------------
+Modules
+
+A module is a symbol with its list of actual parameters.  Derivation
+works on lists of modules.  Module strings in rules are parsed once
+into templates, whose parameters are expressions to be evaluated for
+each rewrite.
+*/
+
+/// A symbol with its actual parameters
+#[derive(Debug, Clone, PartialEq)]
+pub struct Module {
+    /// The symbol
+    pub sym:    Sym,
+    /// Its actual parameters, if any
+    pub params: Vec<f64>,
+}
+
+struct ModuleTemplate {
+    sym:  Sym,
+    args: Vec<Expr>,
+}
+
+/*
+A symbol is the name of a module.  Most are a single character, as in
+ABOP, but an LSys may declare longer names, such as "Apex" or "A_1",
+in its list of symbols.  Strings are split into symbols by taking the
+longest declared name that matches, or else a single character.  So
+once "Apex" is declared "ApexF" is "Apex" then "F", and until then it
+is "A", "p", "e", "x" and "F".  Actions are always single characters.
+Symbols are shared as Arc, so that derived modules can be sent to other
+threads.
+*/
+/// The name of a module
+pub type Sym = Arc<str>;
+
+// length in bytes of the symbol that src starts with
+fn sym_len(symbols:&[String], src:&str) -> usize {
+    let mut len = src.chars().next().map_or(0, char::len_utf8);
+    for name in symbols {
+        if name.len() > len && src.starts_with(name.as_str()) {
+            len = name.len();
+        }
+    }
+    len
+}
+
+// true if sym is one of the given characters
+fn sym_in(sym:&str, chars:&str) -> bool {
+    let mut cs = sym.chars();
+    matches!((cs.next(), cs.next()), (Some(c), None) if chars.contains(c))
+}
+
+// split a list of symbols without parameters, such as the ignore list
+fn symbols_split<'a>(symbols:&[String], list:&'a str) -> Vec<&'a str> {
+    let mut out = vec!();
+    let mut rest = list.trim_start();
+    while !rest.is_empty() {
+        let n = sym_len(symbols, rest);
+        out.push(&rest[..n]);
+        rest = rest[n..].trim_start();
+    }
+    out
+}
+
+/*
+Split a module string into symbols, each with the text of its
+arguments.  White space between modules is ignored.
+*/
+fn module_string_split<'a>(symbols:&[String], src:&'a str)
+    -> Result<Vec<(&'a str,Vec<String>)>,String>
+{
+    let mut out:Vec<(&str,Vec<String>)> = vec!();
+    let mut rest = src.trim_start();
+    while !rest.is_empty() {
+        if rest.starts_with(')') {
+            return Err(format!("Unbalanced ')' in '{src}'"));
+        }
+        if rest.starts_with('(') {
+            return Err(format!("Parameters without a symbol in '{src}'"));
+        }
+        let n = sym_len(symbols, rest);
+        let sym = &rest[..n];
+        rest = &rest[n..];
+        let mut args = vec!();
+        if let Some(inner) = rest.strip_prefix('(') {
+            let mut depth = 0;
+            let mut arg = String::new();
+            let mut end = None;
+            for (k,a) in inner.char_indices() {
+                match a {
+                    ')' if depth == 0 => {
+                        end = Some(k);
+                        break;
+                    }
+                    ',' if depth == 0 => {
+                        args.push(arg.trim().to_string());
+                        arg.clear();
+                        continue;
+                    }
+                    '(' => depth += 1,
+                    ')' => depth -= 1,
+                    _ => (),
+                }
+                arg.push(a);
+            }
+            let Some(end) = end else {
+                return Err(format!("Unbalanced '(' in '{src}'"));
+            };
+            if !(args.is_empty() && arg.trim().is_empty()) {
+                args.push(arg.trim().to_string());
+            }
+            rest = &inner[end+1..];
+        }
+        out.push((sym,args));
+        rest = rest.trim_start();
+    }
+    Ok(out)
+}
+
+fn templates_parse(
+    lsys:&LSys,
+    src:&str,
+    names:&[String],
+) -> Result<Vec<ModuleTemplate>,String> {
+    let mut out = vec!();
+    for (sym,args) in module_string_split(&lsys.symbols, src)? {
+        let args = args.iter()
+            .map(|a| expr_parse(a, names, &lsys.consts))
+            .collect::<Result<Vec<Expr>,String>>()?;
+        out.push(ModuleTemplate {sym: Arc::from(sym), args});
+    }
+    Ok(out)
+}
+
+fn templates_eval(ts:&[ModuleTemplate], values:&[f64], out:&mut Vec<Module>) {
+    for t in ts {
+        out.push(Module {
+            sym:    t.sym.clone(),
+            params: t.args.iter().map(|e| expr_eval(e, values)).collect(),
+        });
+    }
+}
+
+// parse a module string with constant parameters, such as the start rule
+/// Parse a string of modules, such as a start rule
+pub fn modules_parse(lsys:&LSys, src:&str) -> Result<Vec<Module>,LSysError> {
+    let mut out = vec!();
+    templates_eval(&templates_parse(lsys, src, &[])?, &[], &mut out);
+    Ok(out)
+}
+
+/*----------------------------------------------------------------------
+Pseudo random numbers for stochastic rules
+
+This is splitmix64, kept here rather than pulled from a crate so that
+the figure drawn for a given seed never changes from one build to
+the next.
+
+Each derivation step draws from its own stream.  The choice made for
+a symbol then depends only on the seed, the step, and the position of
+the symbol within that step.
+*/
+
+/// Random numbers for stochastic rules
+pub struct Rng {
+    state: u64,
+}
+
+fn rng_new(seed:u64, stream:u64) -> Rng {
+    let mut rng = Rng { state: seed };
+    let mixed = rng_next_u64(&mut rng);
+    Rng { state: mixed ^ stream.wrapping_mul(0x9e37_79b9_7f4a_7c15) }
+}
+
+fn rng_next_u64(rng:&mut Rng) -> u64 {
+    rng.state = rng.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    let mut z = rng.state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+// uniform in [0,1)
+fn rng_next_f64(rng:&mut Rng) -> f64 {
+    (rng_next_u64(rng) >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/*
+Pick the replacement for one symbol.  A stochastic successor always
+consumes exactly one random number, even when there is only one
+alternative, so that streams stay aligned when rules are edited.
+*/
+fn successor_choose<'a>(prod:&'a Production, rng:&mut Rng) -> &'a [ModuleTemplate] {
+    if !prod.stochastic {
+        return &prod.alts[0].1;
+    }
+    let total:f64 = prod.alts.iter().map(|(w,_)| w.max(0.0)).sum();
+    let mut r = rng_next_f64(rng) * total;
+    for (w,ts) in &prod.alts {
+        r -= w.max(0.0);
+        if r < 0.0 {
+            return ts;
+        }
+    }
+    // rounding, or all weights zero
+    prod.alts.last().map_or(&[], |(_,ts)| ts)
+}
+
+/*----------------------------------------------------------------------
+Rule left hand sides
+
+Split the key of a rule into left context, predecessor, right context
+and condition.  The rules are then grouped by predecessor symbol, with
+the most specific rules first, for lookup during rewriting.
+
+The formal parameters of all modules in the left hand side are in
+scope for the condition and the successor, in order from left to
+right.
+*/
+
+struct Pattern {
+    sym:     Sym,
+    formals: Vec<String>,
+}
+
+struct Production {
+    left:       Vec<Pattern>,     // left context, may be empty
+    pred:       Pattern,          // the symbol replaced
+    right:      Vec<Pattern>,     // right context, may be empty
+    cond:       Option<Expr>,     // condition, if any
+    alts:       Vec<(f64,Vec<ModuleTemplate>)>,  // weighted successors
+    stochastic: bool,             // choose among alts at random
+}
+
+type Productions = HashMap<Sym,Vec<Production>>;
+
+fn patterns_parse(lsys:&LSys, src:&str) -> Result<Vec<Pattern>,String> {
+    let mut out = vec!();
+    for (sym,formals) in module_string_split(&lsys.symbols, src)? {
+        for f in &formals {
+            let mut chars = f.chars();
+            let ok = chars.next().is_some_and(|c| c.is_alphabetic() || c == '_')
+                && chars.all(|c| c.is_alphanumeric() || c == '_');
+            if !ok {
+                return Err(format!("Formal parameter '{f}' is not a name in '{src}'"));
+            }
+        }
+        out.push(Pattern {sym: Arc::from(sym), formals});
+    }
+    Ok(out)
+}
+
+type RuleLhs = (Vec<Pattern>,Pattern,Vec<Pattern>,Option<String>);
+
+fn rule_lhs_parse(lsys:&LSys, lhs:&str) -> Result<RuleLhs,String> {
+    let (lhs,cond) = match lhs.split_once(':') {
+        Some((l,c)) => (l,Some(c.trim().to_string())),
+        None        => (lhs,None),
+    };
+    let (left,rest) = match lhs.split_once('<') {
+        Some((l,r)) => (l,r),
+        None        => ("",lhs),
+    };
+    let (pred,right) = match rest.split_once('>') {
+        Some((p,r)) => (p,r),
+        None        => (rest,""),
+    };
+    if pred.trim().is_empty() || left.contains('>') || right.contains('>') {
+        return Err(format!(
+            "Rule '{lhs}' can not rewrite or match '<' or '>', which separate contexts"));
+    }
+    let mut pred = patterns_parse(lsys, pred)?;
+    if pred.len() != 1 {
+        return Err(format!("Rule '{lhs}' must have exactly one predecessor symbol"));
+    }
+    Ok((patterns_parse(lsys, left)?, pred.remove(0), patterns_parse(lsys, right)?, cond))
+}
+
+fn rules_productions(lsys:&LSys, rules:&Rules) -> Result<Productions,String> {
+    let mut keys:Vec<&String> = rules.keys().collect();
+    keys.sort();
+    let mut prods:Productions = HashMap::new();
+    for lhs in keys {
+        let (left,pred,right,cond) = rule_lhs_parse(lsys, lhs)?;
+        let names:Vec<String> = left.iter().chain([&pred]).chain(&right)
+            .flat_map(|p| p.formals.iter().cloned())
+            .collect();
+        let cond = match cond {
+            Some(c) => Some(expr_parse(&c, &names, &lsys.consts)?),
+            None    => None,
+        };
+        let (alts,stochastic) = match &rules[lhs] {
+            Successor::Basic(s) => (vec![(1.0, s.as_str())], false),
+            Successor::Stochastic(v) => (v.iter().map(|(w,s)| (*w,s.as_str())).collect(), true),
+        };
+        let alts = alts.into_iter()
+            .map(|(w,s)| Ok((w,templates_parse(lsys, s, &names)?)))
+            .collect::<Result<Vec<_>,String>>()?;
+        if alts.is_empty() {
+            return Err(format!("Rule '{lhs}' has no successors"));
+        }
+        let p = Production {left,pred,right,cond,alts,stochastic};
+        prods.entry(p.pred.sym.clone()).or_default().push(p);
+    }
+    for v in prods.values_mut() {
+        // stable, so equally specific rules stay sorted by key
+        v.sort_by_key(|p| (
+            std::cmp::Reverse(p.left.len() + p.right.len()),
+            p.cond.is_none(),
+        ));
+    }
+    Ok(prods)
+}
+
+fn rules_check(lsys:&LSys, rules:&Rules) -> Result<(),String> {
+    rules_productions(lsys, rules).map(|_| ())
+}
+
+/*----------------------------------------------------------------------
+Context matching
+
+Contexts are matched in a bracketed string the way ABOP defines it.
+Looking left, a "[" is stepped over to reach the parent of the branch,
+and a complete "[...]" subtree is skipped over since it is a sibling.
+Looking right, complete subtrees are skipped unless the context itself
+asks for a "[", and a "]" in the context skips to the end of the
+current branch.  Symbols in the ignore list are skipped in both
+directions.
+
+On success the positions of the modules matched are returned, in
+order from left to right, so their parameters can be bound.
+*/
+
+fn pattern_match(p:&Pattern, m:&Module) -> bool {
+    p.sym == m.sym && p.formals.len() == m.params.len()
+}
+
+fn context_left_match(
+    s:&[Module],
+    i:usize,
+    ctx:&[Pattern],
+    ignore:&[&str],
+) -> Option<Vec<usize>> {
+    let mut matched = vec!();
+    let mut j = i;
+    for p in ctx.iter().rev() {
+        loop {
+            if j == 0 {
+                return None;
+            }
+            j -= 1;
+            if &*s[j].sym == "]" {
+                // skip sibling subtree back to its "["
+                let mut depth = 0;
+                loop {
+                    match &*s[j].sym {
+                        "]" => depth += 1,
+                        "[" => depth -= 1,
+                        _   => (),
+                    }
+                    if depth == 0 {
+                        break;
+                    }
+                    if j == 0 {
+                        return None;
+                    }
+                    j -= 1;
+                }
+            }
+            else if &*s[j].sym != "[" && !ignore.contains(&&*s[j].sym) {
+                break;
+            }
+        }
+        if !pattern_match(p, &s[j]) {
+            return None;
+        }
+        matched.push(j);
+    }
+    matched.reverse();
+    Some(matched)
+}
+
+fn context_right_match(
+    s:&[Module],
+    i:usize,
+    ctx:&[Pattern],
+    ignore:&[&str],
+) -> Option<Vec<usize>> {
+    let mut matched = vec!();
+    let mut j = i + 1;
+    for p in ctx {
+        if &*p.sym == "]" {
+            // skip to end of current branch
+            let mut depth = 0;
+            loop {
+                if j >= s.len() {
+                    return None;
+                }
+                match &*s[j].sym {
+                    "[" => depth += 1,
+                    "]" if depth == 0 => break,
+                    "]" => depth -= 1,
+                    _   => (),
+                }
+                j += 1;
+            }
+            matched.push(j);
+            j += 1;
+            continue;
+        }
+        loop {
+            while j < s.len() && ignore.contains(&&*s[j].sym) {
+                j += 1;
+            }
+            if &*p.sym == "[" || j >= s.len() || &*s[j].sym != "[" {
+                break;
+            }
+            // skip subtree
+            let mut depth = 0;
+            loop {
+                match &*s[j].sym {
+                    "[" => depth += 1,
+                    "]" => depth -= 1,
+                    _   => (),
+                }
+                j += 1;
+                if depth == 0 || j >= s.len() {
+                    break;
+                }
+            }
+        }
+        if j >= s.len() || !pattern_match(p, &s[j]) {
+            return None;
+        }
+        matched.push(j);
+        j += 1;
+    }
+    Some(matched)
+}
+
+/*
+Check that a production applies to the module at position i,
+and if so, collect the values of its formal parameters.
+*/
+fn production_match(
+    p:&Production,
+    s:&[Module],
+    i:usize,
+    ignore:&[&str],
+    values:&mut Vec<f64>,
+) -> bool {
+    if !pattern_match(&p.pred, &s[i]) {
+        return false;
+    }
+    let Some(left) = context_left_match(s, i, &p.left, ignore) else {
+        return false;
+    };
+    let Some(right) = context_right_match(s, i, &p.right, ignore) else {
+        return false;
+    };
+    values.clear();
+    for j in left.into_iter().chain([i]).chain(right) {
+        values.extend_from_slice(&s[j].params);
+    }
+    p.cond.as_ref().is_none_or(|c| expr_eval(c, values) != 0.0)
+}
+
+/*----------------------------------------------------------------------
+Elaborate Lindenmayer System
+
+Apply rules iteratively until specified order is reached.
+Rewrite every module of the old list in parallel, looking up its
+rules, and checking context and condition against the old list.  The
+first rule that matches supplies the replacement, which is appended to
+the new list.  Modules with no matching rule are copied unchanged.
+*/
+
+// rewrite every module once, using one table of productions
+fn rules_apply_step(
+    lsys:&LSys,
+    prods:&Productions,
+    old:&[Module],
+    step:i32,
+) -> Vec<Module> {
+    let mut rng = rng_new(lsys.seed, step as u64);
+    let ignore = symbols_split(&lsys.symbols, &lsys.ignore);
+    let mut values:Vec<f64> = vec!();
+    let mut new = vec!();
+    for (i,m) in old.iter().enumerate() {
+        let prod = prods.get(&m.sym).and_then(|v| v.iter().find(|p|
+            production_match(p, old, i, &ignore, &mut values)
+        ));
+        match prod {
+            Some(p) => templates_eval(successor_choose(p,&mut rng), &values, &mut new),
+            None    => new.push(m.clone()),
+        }
+    }
+    new
+}
+
+fn rules_apply_basic(
+    lsys:&LSys,
+    rules:&Rules,
+    start:&[Module],
+    order:i32,
+) -> Result<Vec<Module>,LSysError> {
+    let prods = rules_productions(lsys, rules)?;
+    let mut new = start.to_vec();
+    for step in 0..order {
+        new = rules_apply_step(lsys, &prods, &new, step);
+    }
+    Ok(new)
+}
+
+/*----------------------------------------------------------------------
+Table L-systems
+
+A table L-system (T0L) has several named tables of rules, and a
+schedule saying which table to use at each derivation step.  Each
+entry of the schedule is a table name and a number of steps, so
+
+    "schedule" : [ ["grow",3], ["flower",1] ]
+
+uses table "grow" for steps 1 to 3, then "flower" for step 4.  After
+the schedule runs out its last table goes on being used.  The name
+"rules" refers to the main rules of the LSys, which are also what is
+used at every step when there is no schedule.
+*/
+
+fn lsys_table<'a>(lsys:&'a LSys, name:&str) -> Option<&'a Rules> {
+    if name == "rules" {
+        Some(&lsys.rules)
+    }
+    else {
+        lsys.tables.get(name)
+    }
+}
+
+// name of the table used at a derivation step, counting from 0
+fn lsys_table_at(lsys:&LSys, step:i32) -> &str {
+    let mut end = 0;
+    for (name,steps) in &lsys.schedule {
+        end += steps;
+        if step < end {
+            return name;
+        }
+    }
+    lsys.schedule.last().map_or("rules", |(name,_)| name)
+}
+
+// the tables lsys_table_at gives for some step, in schedule order
+fn lsys_tables_used(lsys:&LSys) -> Vec<&str> {
+    let mut used:Vec<&str> = vec!();
+    let (mut end, mut reached) = (0, 0);
+    for (name,steps) in &lsys.schedule {
+        end += steps;
+        if end > reached {
+            reached = end;
+            used.push(name);
+        }
+    }
+    let last = lsys.schedule.last().map_or("rules", |(name,_)| name.as_str());
+    if !used.contains(&last) {
+        used.push(last);
+    }
+    used
+}
+
+fn lsys_table_productions(lsys:&LSys, name:&str) -> Result<Productions,LSysError> {
+    let Some(rules) = lsys_table(lsys, name) else {
+        return Err(LSysError::Invalid(format!("Schedule refers to unknown table '{name}'")));
+    };
+    Ok(rules_productions(lsys, rules)?)
+}
+
+fn lsys_check_tables(lsys:&LSys) -> Result<(),String> {
+    if lsys.tables.contains_key("rules") {
+        return Err("Table name 'rules' is reserved for the main rules".to_string());
+    }
+    for (name,rules) in &lsys.tables {
+        rules_check(lsys, rules)
+            .map_err(|why| format!("Table '{name}': {why}"))?;
+    }
+    for (name,steps) in &lsys.schedule {
+        if lsys_table(lsys, name).is_none() {
+            return Err(format!("Schedule refers to unknown table '{name}'"));
+        }
+        if *steps < 0 {
+            return Err(format!("Schedule has negative steps for table '{name}'"));
+        }
+    }
+    Ok(())
+}
+
+/// Check things serde can not, such as the form of rules
+pub fn lsys_check(lsys:&LSys) -> Result<(),LSysError> {
+    for name in &lsys.symbols {
+        let ok = name.chars().count() > 1
+            && name.chars().all(|c| c.is_alphanumeric() || c == '_');
+        if !ok {
+            return Err(LSysError::Invalid(format!(
+                "Symbol name '{name}' must be letters, digits or '_', \
+                and more than one of them")));
+        }
+    }
+    let draws = symbols_split(&lsys.symbols, &lsys.draws);
+    let moves = symbols_split(&lsys.symbols, &lsys.moves);
+    for c in draws.iter().chain(&moves) {
+        if sym_in(c, ACTIONS) {
+            return Err(LSysError::Invalid(format!("Symbol '{c}' is already an action")));
+        }
+        if draws.contains(c) && moves.contains(c) {
+            return Err(LSysError::Invalid(format!("Symbol '{c}' can not both draw and move")));
+        }
+    }
+    modules_parse(lsys, &lsys.start)?;
+    rules_check(lsys, &lsys.rules)?;
+    rules_check(lsys, &lsys.post_rules)?;
+    Ok(lsys_check_tables(lsys)?)
+}
+
+// true if any rule has weighted alternatives
+fn lsys_is_stochastic(lsys:&LSys) -> bool {
+    lsys.rules.values().chain(lsys.post_rules.values())
+        .chain(lsys.tables.values().flat_map(|t| t.values()))
+        .any(|succ| matches!(succ, Successor::Stochastic(_)))
+}
+
+/*----------------------------------------------------------------------
+remove non-action characters from LSys rules, keeping the extra draw
+and move symbols of the LSys
+
+The draws and moves lists are split into sets once, by lsys_actions,
+and kept by whatever looks at every module, such as the turtle and the
+derivation, rather than split again for each module.
+*/
+
+/// The extra draw and move symbols of an LSys, see lsys_actions
+#[derive(Debug, Clone, Default)]
+pub struct Actions {
+    /// Symbols which draw like "F"
+    pub draws: HashSet<Sym>,
+    /// Symbols which move like "f"
+    pub moves: HashSet<Sym>,
+}
+
+/// The extra draw and move symbols of lsys
+pub fn lsys_actions(lsys:&LSys) -> Actions {
+    let set = |list:&str| symbols_split(&lsys.symbols, list).into_iter().map(Sym::from).collect();
+    Actions { draws: set(&lsys.draws), moves: set(&lsys.moves) }
+}
+
+/// Whether sym draws a line
+pub fn actions_is_draw(acts:&Actions, sym:&str) -> bool {
+    sym_in(sym, "FG") || acts.draws.contains(sym)
+}
+
+/// Whether the turtle acts on sym
+pub fn actions_is_action(acts:&Actions, sym:&str) -> bool {
+    sym_in(sym, ACTIONS) || acts.draws.contains(sym) || acts.moves.contains(sym)
+}
+
+/// Only the modules which draw or move the turtle
+pub fn rules_minimize(lsys:&LSys, rules:&[Module]) -> Vec<Module> {
+    let acts = lsys_actions(lsys);
+    let mut out = vec!();
+    for rule in rules {
+        if actions_is_action(&acts, &rule.sym) {
+            out.push(rule.clone());
+        }
+    }
+    out
+}
+
+/*----------------------------------------------------------------------
+Higher level rule application
+
+Apply both main rules and post rules.
+The main rules, or the tables given by the schedule, are applied once
+for each step of the order.
+The post rule substitution is used to allow use of rules from
+sources that presume implicit drawing on rules other than F.
+After all rule application, do minimization.
+*/
+
+/// Apply the main rules, or tables, to start for order steps
+pub fn lsys_apply_tables(lsys:&LSys, start:&[Module], order:i32)
+    -> Result<Vec<Module>,LSysError>
+{
+    let mut tables:HashMap<&str,Productions> = HashMap::new();
+    let mut new = start.to_vec();
+    for step in 0..order {
+        let name = lsys_table_at(lsys,step);
+        if !tables.contains_key(name) {
+            tables.insert(name, lsys_table_productions(lsys,name)?);
+        }
+        new = rules_apply_step(lsys,&tables[name],&new,step);
+    }
+    Ok(new)
+}
+
+/// The whole string derived to order, with post rules, minimized
+pub fn lsys_apply_rules(lsys:&LSys,order:i32) -> Result<Vec<Module>,LSysError> {
+    let start = modules_parse(lsys,&lsys.start)?;
+    // do rule substition
+    let basic = lsys_apply_tables(lsys,&start,order)?;
+    // do post rule substitution
+    let post = rules_apply_basic(lsys,&lsys.post_rules,&basic,1)?;
+    Ok(rules_minimize(lsys,&post))
+}
+
+/*----------------------------------------------------------------------
+Lazy derivation
+
+Rather than build the whole string for every step, the final string
+can be produced one module at a time, by expanding each module depth
+first down to the order wanted.  Only the successors along the current
+path are kept, so memory grows with the order and the length of the
+successors, and not with the length of the result.  This allows
+drawing orders whose strings would not fit in memory.  A frame is
+dropped as its last module is taken, so a chain such as "F -> F" needs
+no memory for the steps it has passed.
+
+Productions are parsed once for each table the schedule uses, and the
+random stream of a step is made when the step is first reached, then
+dropped once no module can come back to it.
+
+Within each step modules are still rewritten from left to right, so
+stochastic choices, which draw from one random stream per step, come
+out the same as with lsys_apply_rules.
+
+Context can only be matched in the full string, so an LSys with
+context sensitive rules is derived with lsys_apply_rules instead.
+*/
+
+struct Frame {
+    level:   i32,           // steps already applied to these modules
+    modules: Vec<Module>,
+    next:    usize,         // the next module to expand
+}
+
+struct Derivation<'a> {
+    lsys:   &'a LSys,
+    order:  i32,
+    names:  Vec<&'a str>,       // tables used
+    prods:  Vec<Productions>,   // for each of names
+    post:   Productions,
+    acts:   Actions,
+    rngs:   VecDeque<Rng>,      // for each step from rng_level on
+    rng_level: i32,
+    post_rng: Rng,
+    stack:  Vec<Frame>,
+    values: Vec<f64>,
+}
+
+fn lsys_is_context_free(lsys:&LSys) -> bool {
+    lsys.rules.keys().chain(lsys.post_rules.keys())
+        .chain(lsys.tables.values().flat_map(|t| t.keys()))
+        .all(|lhs| match rule_lhs_parse(lsys, lhs) {
+            Ok((left,_,right,_)) => left.is_empty() && right.is_empty(),
+            Err(_) => false,
+        })
+}
+
+/// The modules of lsys_apply_rules, one at a time.  Rules are all
+/// parsed here, so the derivation itself can not fail.
+pub fn lsys_derive(lsys:&LSys, order:i32)
+    -> Result<Box<dyn Iterator<Item=Module> + '_>,LSysError>
+{
+    if !lsys_is_context_free(lsys) {
+        return Ok(Box::new(lsys_apply_rules(lsys,order)?.into_iter()));
+    }
+    let order = order.max(0);
+    // the steps where each entry of the schedule starts, and so all the
+    // tables used before order
+    let mut starts = vec![0];
+    for (_,steps) in &lsys.schedule {
+        starts.push(starts[starts.len()-1] + steps);
+    }
+    let mut names:Vec<&str> = vec!();
+    let mut prods = vec!();
+    for step in starts.into_iter().filter(|&s| s < order) {
+        let name = lsys_table_at(lsys,step);
+        if !names.contains(&name) {
+            names.push(name);
+            prods.push(lsys_table_productions(lsys,name)?);
+        }
+    }
+    let start = modules_parse(lsys,&lsys.start)?;
+    let stack = if start.is_empty() { vec!() } else { vec![Frame { level: 0, modules: start, next: 0 }] };
+    Ok(Box::new(Derivation {
+        lsys,
+        order,
+        names,
+        prods,
+        post:   rules_productions(lsys,&lsys.post_rules)?,
+        acts:   lsys_actions(lsys),
+        rngs:   VecDeque::new(),
+        rng_level: 0,
+        post_rng: rng_new(lsys.seed, 0),
+        stack,
+        values: vec!(),
+    }))
+}
+
+// the random stream of a step, of those from rng_level on, made when first needed
+fn derivation_rng(rngs:&mut VecDeque<Rng>, rng_level:i32, seed:u64, level:i32) -> &mut Rng {
+    let i = (level - rng_level) as usize;
+    while rngs.len() <= i {
+        let step = rng_level + rngs.len() as i32;
+        rngs.push_back(rng_new(seed, step as u64));
+    }
+    &mut rngs[i]
+}
+
+impl Iterator for Derivation<'_> {
+    type Item = Module;
+
+    fn next(&mut self) -> Option<Module> {
+        loop {
+            let frame = self.stack.last_mut()?;
+            let mut level = frame.level;
+            let m = frame.modules[frame.next].clone();
+            frame.next += 1;
+            if frame.next == frame.modules.len() {
+                self.stack.pop();
+            }
+            // steps above every frame, and m, are never rewritten again
+            let lowest = self.stack.first().map_or(level, |f| f.level.min(level));
+            while self.rng_level < lowest && !self.rngs.is_empty() {
+                self.rngs.pop_front();
+                self.rng_level += 1;
+            }
+            if self.rngs.is_empty() {
+                self.rng_level = lowest;
+            }
+
+            // a module with no rule goes down unchanged, without a frame
+            loop {
+                if level > self.order {
+                    if actions_is_action(&self.acts, &m.sym) {
+                        return Some(m);
+                    }
+                    break;
+                }
+                let prods = if level < self.order {
+                    let name = lsys_table_at(self.lsys, level);
+                    &self.prods[self.names.iter().position(|n| *n == name).unwrap_or(0)]
+                }
+                else {
+                    &self.post
+                };
+                let values = &mut self.values;
+                let prod = prods.get(&m.sym).and_then(|v| v.iter().find(|p|
+                    production_match(p, std::slice::from_ref(&m), 0, &[], values)
+                ));
+                if let Some(p) = prod {
+                    let rng = if level == self.order {
+                        &mut self.post_rng
+                    }
+                    else {
+                        derivation_rng(&mut self.rngs, self.rng_level, self.lsys.seed, level)
+                    };
+                    let mut modules = vec!();
+                    templates_eval(successor_choose(p,rng), values, &mut modules);
+                    if !modules.is_empty() {
+                        self.stack.push(Frame { level: level + 1, modules, next: 0 });
+                    }
+                    break;
+                }
+                level += 1;
+            }
+        }
+    }
+}
+
+/*----------------------------------------------------------------------
+Turtle interpretation
+
+The turtle keeps its state in three dimensions, as in ABOP section
+1.5.  That is a position, and an orientation given by three unit
+vectors, heading H, left L and up U.  It starts at the origin heading
+along x, with left along y and up along z.  So as long as only "+", "-"
+and "|" are used it stays in the x-y plane, and "+" turns counter
+clockwise in the usual mathematical sense.
+
+Besides the actions listed at the top, the turtle knows:
+& Pitch down by turning angle
+^ Pitch up by turning angle
+\ Roll left by turning angle
+/ Roll right by turning angle
+$ Roll until the left vector is horizontal
+
+"Horizontal" means perpendicular to y, which is the up direction of a
+2D drawing.  Like "+" and "-", the 3D turns take an optional angle
+parameter in degrees.
+
+The turtle reports each move, and each line drawn, as an absolute
+position.  This is shared by the 2D drawing actions below, which are a
+projection onto the x-y plane, and by the 3D output in mesh.rs.
+*/
+
+/// A point or vector in 3D
+pub type V3 = [f64;3];
+
+fn v3_add(a:V3, b:V3) -> V3        { [a[0]+b[0], a[1]+b[1], a[2]+b[2]] }
+fn v3_sub(a:V3, b:V3) -> V3        { [a[0]-b[0], a[1]-b[1], a[2]-b[2]] }
+fn v3_scale(a:V3, s:f64) -> V3     { [a[0]*s, a[1]*s, a[2]*s] }
+fn v3_dot(a:V3, b:V3) -> f64       { a[0]*b[0] + a[1]*b[1] + a[2]*b[2] }
+fn v3_cross(a:V3, b:V3) -> V3 {
+    [a[1]*b[2]-a[2]*b[1], a[2]*b[0]-a[0]*b[2], a[0]*b[1]-a[1]*b[0]]
+}
+fn v3_len(a:V3) -> f64             { v3_dot(a,a).sqrt() }
+
+// rotate the pair of unit vectors (a,b) by angle in the a to b direction
+fn v3_rotate_pair(a:V3, b:V3, angle:f64) -> (V3,V3) {
+    let (s,c) = angle.sin_cos();
+    (v3_add(v3_scale(a,c), v3_scale(b,s)), v3_sub(v3_scale(b,c), v3_scale(a,s)))
+}
+
+#[derive(Clone)]
+struct Turtle {
+    pos:   V3,
+    h:     V3,
+    l:     V3,
+    u:     V3,
+    color: usize,       // index into color map
+    width: f64,         // line width, in strokes
+    step:  f64,         // line length, in steps
+}
+
+/*
+A change of color or width is only reported just before something is
+drawn with it, so that branches which only move, and the restore at
+each "]", do not split the output into many pieces.
+*/
+/// What the turtle does, in absolute positions
+pub enum TAct {
+    /// Move without drawing
+    MoveTo(V3),
+    /// Draw a line
+    LineTo(V3),
+    /// A filled polygon
+    Polygon(Vec<V3>),
+    /// Color map index and line width
+    Style(usize,f64),
+}
+
+fn turtle_style(t:&Turtle, shown:&mut (usize,f64), out:&mut impl FnMut(TAct)) {
+    if (t.color,t.width) != *shown {
+        *shown = (t.color,t.width);
+        out(TAct::Style(t.color,t.width));
+    }
+}
+
+// close enough to be the same vertex
+fn v3_near(a:V3, b:V3) -> bool {
+    v3_len(v3_sub(a,b)) < 1e-9
+}
+
+// record a polygon vertex, if a polygon is open
+fn polygon_vertex(polygons:&mut [Vec<V3>], p:V3) {
+    if let Some(poly) = polygons.last_mut() {
+        if !poly.last().is_some_and(|&q| v3_near(p,q)) {
+            poly.push(p);
+        }
+    }
+}
+
+/*
+A "]" or "}" with nothing open is an error, found at the position of
+the module in rules.  Anything left open at the end is not, a "[" has
+nothing to undo and an unclosed "{" is never filled.
+*/
+/// Run the turtle over rules, passing each action to out
+pub fn turtle_run(
+    lsys:&LSys,
+    rules:impl IntoIterator<Item=Module>,
+    out:&mut impl FnMut(TAct),
+) -> Result<(),LSysError> {
+    let mut stack:Vec<Turtle> = vec!();
+    let mut polygons:Vec<Vec<V3>> = vec!();
+    let mut t = Turtle {
+        pos:   [0.0, 0.0, 0.0],
+        h:     [1.0, 0.0, 0.0],
+        l:     [0.0, 1.0, 0.0],
+        u:     [0.0, 0.0, 1.0],
+        color: 0,
+        width: 1.0,
+        step:  1.0,
+    };
+    let factor = if lsys.length_factor == 0.0 { 1.0 } else { lsys.length_factor };
+    let thin = if lsys.width_factor == 0.0 { WIDTH_FACTOR } else { lsys.width_factor };
+    let acts = lsys_actions(lsys);
+    let mut shown = (t.color,t.width);
+    for (pos,rule) in rules.into_iter().enumerate() {
+        // first parameter, if any, overrides the default
+        let param = |default:f64| rule.params.first().copied().unwrap_or(default);
+        let angle = param(lsys.angle) * PI / 180.0;
+        let sym =
+            if      acts.draws.contains(&rule.sym) { "F" }
+            else if acts.moves.contains(&rule.sym) { "f" }
+            else                                   { &rule.sym };
+        match sym {
+            // forward
+            "F" => {
+                t.pos = v3_add(t.pos, v3_scale(t.h, param(t.step)));
+                polygon_vertex(&mut polygons, t.pos);
+                turtle_style(&t, &mut shown, out);
+                out(TAct::LineTo(t.pos));
+            }
+            "f" => {
+                t.pos = v3_add(t.pos, v3_scale(t.h, param(t.step)));
+                polygon_vertex(&mut polygons, t.pos);
+                out(TAct::MoveTo(t.pos));
+            }
+            // forward, leaving any polygon alone
+            "G" => {
+                t.pos = v3_add(t.pos, v3_scale(t.h, param(t.step)));
+                turtle_style(&t, &mut shown, out);
+                out(TAct::LineTo(t.pos));
+            }
+            "g" => {
+                t.pos = v3_add(t.pos, v3_scale(t.h, param(t.step)));
+                out(TAct::MoveTo(t.pos));
+            }
+            // turn, about U
+            "+" => (t.h,t.l) = v3_rotate_pair(t.h, t.l,  angle),
+            "-" => (t.h,t.l) = v3_rotate_pair(t.h, t.l, -angle),
+            "|" => (t.h,t.l) = (v3_scale(t.h,-1.0), v3_scale(t.l,-1.0)),
+            // pitch, about L
+            "&" => (t.h,t.u) = v3_rotate_pair(t.h, t.u, -angle),
+            "^" => (t.h,t.u) = v3_rotate_pair(t.h, t.u,  angle),
+            // roll, about H
+            "\\" => (t.l,t.u) = v3_rotate_pair(t.l, t.u, -angle),
+            "/"  => (t.l,t.u) = v3_rotate_pair(t.l, t.u,  angle),
+            "$" => {
+                let l = v3_cross([0.0, 1.0, 0.0], t.h);
+                let n = v3_len(l);
+                // nothing to do when heading straight up or down
+                if n > 1e-9 {
+                    t.l = v3_scale(l, 1.0/n);
+                    t.u = v3_cross(t.h, t.l);
+                }
+            }
+            "[" => {
+                stack.push(t.clone());
+            }
+            "]" => {
+                let Some(top) = stack.pop() else {
+                    return Err(LSysError::Unbalanced { bracket: ']', pos });
+                };
+                t = top;
+                out(TAct::MoveTo(t.pos));
+            }
+            // polygons
+            "{" => {
+                polygons.push(vec![t.pos]);
+            }
+            "." => {
+                polygon_vertex(&mut polygons, t.pos);
+            }
+            "}" => {
+                let Some(mut poly) = polygons.pop() else {
+                    return Err(LSysError::Unbalanced { bracket: '}', pos });
+                };
+                if poly.len() > 1 && v3_near(poly[0], poly[poly.len()-1]) {
+                    poly.pop();
+                }
+                if poly.len() >= 3 {
+                    turtle_style(&t, &mut shown, out);
+                    out(TAct::Polygon(poly));
+                }
+            }
+            // color and width
+            "'" => {
+                t.color = match rule.params.first() {
+                    Some(&i) => i.max(0.0) as usize,
+                    None     => t.color + 1,
+                };
+            }
+            "!" => {
+                t.width = param(t.width * thin).max(0.0);
+            }
+            "#" => {
+                t.width = param(1.0).max(0.0);
+            }
+            // line length
+            ">" => t.step *= param(factor),
+            "<" => t.step /= param(factor),
+            _ => {
+                return Err(LSysError::UnknownSymbol(rule.sym.to_string()));
+            }
+        }
+    }
+    Ok(())
+}
+
+/*----------------------------------------------------------------------
+Convert fully elaborated LSys rules into drawing actions, passed on one
+at a time, and find their bounding box. The drawing actions operate in
+an abstract space with
+initial position at (x,y)=(0,0) and all actions having relative motion
+of one unit wrt current position, unless a module parameter says
+otherwise.
+
+Drawing actions are the turtle path projected onto the x-y plane, with
+y scaled by ROTATION to suit the output.  A polygon gives its vertices
+relative to the current position, and does not move it.  A style gives
+the color map index and line width for what follows.
+*/
+
+/// Drawing actions, relative to the current position
+pub enum DAct {
+    /// Move without drawing
+    RmoveTo(f64,f64),
+    /// Draw a line
+    RlineTo(f64,f64),
+    /// A filled polygon, relative to the current position
+    Polygon(Vec<(f64,f64)>),
+    /// Color map index and line width
+    Style(usize,f64),
+}
+
+/// Pass the drawing actions of rules to out, and return their bounding box
+pub fn lsys_dacts(
+    lsys:&LSys,
+    rules:impl IntoIterator<Item=Module>,
+    out:&mut impl FnMut(DAct),
+) -> Result<BBox,LSysError> {
+    // current position and bounding box
+    let (mut x, mut y, mut x0, mut y0, mut x1, mut y1 )
+      : (f64,   f64,   f64,    f64,    f64,    f64,   )
+      = (0.0,   0.0,   0.0,    0.0,    0.0,    0.0,   );
+
+    // starting position
+    out(DAct::RmoveTo(0.0,0.0));
+
+    // do the actions
+    turtle_run(lsys, rules, &mut |tact| {
+        let (xt,yt) = match &tact {
+            TAct::MoveTo(p) | TAct::LineTo(p) => (p[0], p[1] * ROTATION),
+            TAct::Polygon(ps) => {
+                // vertices are all places the turtle has been,
+                // so already inside the bounding box
+                let vs = ps.iter().map(|p| (p[0]-x, p[1]*ROTATION-y)).collect();
+                out(DAct::Polygon(vs));
+                return;
+            }
+            TAct::Style(color,width) => {
+                out(DAct::Style(*color,*width));
+                return;
+            }
+        };
+        match tact {
+            TAct::MoveTo(_) => out(DAct::RmoveTo(xt-x,yt-y)),
+            TAct::LineTo(_) => out(DAct::RlineTo(xt-x,yt-y)),
+            _ => (),
+        }
+        x = xt;  y = yt;
+        // maintain bounding box
+        x0 = f64::min(x0,x);     y0 = f64::min(y0,y);
+        x1 = f64::max(x1,x);     y1 = f64::max(y1,y);
+    })?;
+
+    // adjust bounding box so it can't have zero size
+    // this allows scaling to work even for empty output
+    if f64::abs(x1-x0) < 0.1 { x0 = -0.1;  x1 = 0.1; }
+    if f64::abs(y1-y0) < 0.1 { y0 = -0.1;  y1 = 0.1; }
+
+    Ok((x0,y0,x1,y1))
+}
+
+/*----------------------------------------------------------------------
+Produce svg to draw LSys at specified order to fit in specified
+layout box on page.
+
+The units for drawing actions (Dact) and their associated bounding box
+(abb) are in abstract "steps".
+
+The units used in paths are formally known as "SVG user units" and are
+the same as the so called pixel used in html.  It is defined to be 1/96
+of an inch when printed, which is what is used here.
+
+The units for layout boxes (pbb) are also in pixels.
+
+The source box has relative drawing starting at origin.  So after
+drawing, the center of the bounding box gives the offset of drawing
+center.
+*/
+
+/*
+Scale, in pixels per step, and starting position, in pixels, which fit
+a drawing with bounding box abb into the box pbb.  Also the box in
+pixels which the drawing then covers.
+*/
+pub(crate) fn lsys_fit(abb:&BBox, pbb:&BBox) -> (f64,f64,f64,BBox) {
+    let (px0,py0,px1,py1) = pbb;    // pixels
+    let (ax0,ay0,ax1,ay1) = abb;    // steps
+
+    // get x and y size of source and target boxes
+    let px = (px1-px0) * BOX_USAGE_FRACTION;
+    let py = (py1-py0) * BOX_USAGE_FRACTION;
+    let ax = ax1-ax0;
+    let ay = ay1-ay0;
+
+    // get x and y scale factors
+    let sx = px/ax;
+    let sy = py/ay;
+
+    // pick the smallest scale factor
+    // to avoid going outside the layout box
+    let pixel_per_step = f64::min(sx,sy);
+
+    // find starting position in pixels
+    let x = ((px0+px1)/2.0) -  (((ax0+ax1)/2.0) * pixel_per_step);
+    let y = ((py0+py1)/2.0) -  (((ay0+ay1)/2.0) * pixel_per_step);
+    let drawn = (x + ax0*pixel_per_step, y + ay0*pixel_per_step,
+                 x + ax1*pixel_per_step, y + ay1*pixel_per_step);
+    (pixel_per_step, x, y, drawn)
+}
+
+/// SVG of lsys at order, scaled to fit the box pbb, in pixels
+pub fn lsys_draw_basic(lsys:&LSys, order:i32, pbb:&BBox) -> Result<String,LSysError> {
+    let mut svg = String::new();
+
+    // the drawing is scaled to fit before any of it is written, so
+    // derive twice, first only for the bounding box, rather than keep
+    // every drawing action
+    let abb = lsys_dacts(lsys, lsys_derive(lsys,order)?, &mut |_| ())?;
+    let (pixel_per_step, mut x, mut y, _) = lsys_fit(&abb, pbb);
+
+    // begin path
+    let mut color = lsys_color(lsys,0);
+    svg_path_begin(&mut svg, color, 1.0);
+
+    // iterate over actions convert to path
+    // polygons are collected separately, to follow the path
+    // each change of style ends the path and starts another
+    let mut polygons = String::new();
+    let mut col = 0;
+    lsys_dacts(lsys, lsys_derive(lsys,order)?, &mut |dact| {
+        col += 1;
+        match dact {
+            DAct::RmoveTo(xs,ys) => {
+                x += pixel_per_step * xs;
+                y += pixel_per_step * ys;
+                let svgt = format!("M{:07.2} {:07.2} ",x,y);
+                svg.push_str(&svgt);
+            }
+            DAct::RlineTo(xs,ys) => {
+                x += pixel_per_step * xs;
+                y += pixel_per_step * ys;
+                let svgt = format!("L{:07.2} {:07.2} ",x,y);
+                svg.push_str(&svgt);
+            }
+            DAct::Polygon(vs) => {
+                col -= 1;
+                let points:Vec<String> = vs.iter()
+                    .map(|(xs,ys)| format!("{:.2},{:.2}",
+                        x + pixel_per_step * xs,
+                        y + pixel_per_step * ys))
+                    .collect();
+                polygons.push_str(&format!( indoc! {r#"
+                    <polygon
+                        fill="{fill}"
+                        stroke="none"
+                        points="{points}"
+                    />
+                    "#},
+                    fill = color,
+                    points = points.join(" "),
+                ));
+            }
+            DAct::Style(c,w) => {
+                if col > 1 {
+                    svg.push('\n');
+                }
+                svg.push_str("\"/>\n");
+                color = lsys_color(lsys,c);
+                svg_path_begin(&mut svg, color, w);
+                svg.push_str(&format!("M{:07.2} {:07.2} ",x,y));
+            }
+        }
+        if col >= 5 {
+            svg.push('\n');
+            col = 0;
+        }
+    })?;
+    if col > 0 {
+        svg.push('\n');
+    }
+    // end d attribute of path
+    svg.push_str(r#""/>"#);
+    // filled polygons
+    if !polygons.is_empty() {
+        svg.push('\n');
+        svg.push_str(&polygons);
+    }
+    Ok(svg)
+}
+fn svg_path_begin(svg:&mut String, color:&str, width:f64) {
+    let svg_path_prelude = format!( indoc! {r#"
+        <path
+            stroke="{color}"
+            stroke-width="{stroke_width}"
+            fill="none"
+            d = "
+        "#},
+        color = color,
+        stroke_width = STROKE_WIDTH * width,
+    );
+    svg.push_str(&svg_path_prelude);
+}
+
+/*
+Color for an index into the color map of the LSys, or into the
+default map if it has none.  Indexes wrap around.
+*/
+fn lsys_color(lsys:&LSys, index:usize) -> &str {
+    if lsys.colors.is_empty() {
+        DEFAULT_COLORS[index % DEFAULT_COLORS.len()]
+    }
+    else {
+        &lsys.colors[index % lsys.colors.len()]
+    }
+}
+
+/*----------------------------------------------------------------------
+Automatic choice of orders
+
+Each page shows four orders, in the boxes named by ORDER_BOXES.  When
+the LSys gives fewer than four orders, they are chosen from the number
+of line segments each order draws:
+
+- the first box shows the smallest order which draws anything
+- the third box, and the main box, show the order with the most
+  segments that fit the area of the box, at SEGMENT_AREA each
+- the second box shows an order half way between the first and third
+
+Later boxes always show a higher order than earlier ones.  Any orders
+given by the LSys are used for the last boxes, so that the simplest
+figure is always found automatically.  The boxes before them are then
+chosen as above, but only from orders below the first one given.  When
+there are too few of those, as for "order": [1], the first boxes are
+left empty rather than repeat an order.
+*/
+
+/// The boxes of a page which show an order
+pub static ORDER_BOXES:[&str;4] = ["left", "center", "right", "main"];
+
+/*
+Segment counts of orders 0, 1, 2, ... up to the first that is over
+the limit, which is only derived as far as the limit.  Derivation also
+stops at AUTO_ORDER_MAX, or when strings grow too long without drawing
+much.
+*/
+fn lsys_segment_counts(lsys:&LSys, limit:usize) -> Result<Vec<usize>,LSysError> {
+    let acts = lsys_actions(lsys);
+    let mut counts = vec!();
+    for order in 0..=AUTO_ORDER_MAX {
+        let mut count = 0;
+        let mut length = 0;
+        for m in lsys_derive(lsys, order)? {
+            if actions_is_draw(&acts, &m.sym) {
+                count += 1;
+            }
+            length += 1;
+            if count > limit || length > AUTO_MODULES_MAX {
+                break;
+            }
+        }
+        counts.push(count);
+        if count > limit || length > AUTO_MODULES_MAX {
+            break;
+        }
+    }
+    Ok(counts)
+}
+
+/*
+The order, not below the given one, with the most segments within the
+limit.  Of orders with equal counts the smallest is taken, so that a
+figure which stops growing is not derived again and again.
+*/
+fn orders_pick(counts:&[usize], limit:usize, at_least:usize) -> usize {
+    let mut best = at_least;
+    for (order,&count) in counts.iter().enumerate().skip(at_least) {
+        if count <= limit && count > counts.get(best).copied().unwrap_or(0) {
+            best = order;
+        }
+    }
+    best
+}
+
+/// The orders to show in the last of ORDER_BOXES, as many as there are
+/// different orders to show, but never more than ORDER_BOXES
+pub fn lsys_orders(lsys:&LSys, lb:&LayoutBoxes) -> Result<Vec<i32>,LSysError> {
+    let n = ORDER_BOXES.len();
+    if lsys.order.len() >= n {
+        return Ok(lsys.order[0..n].to_vec());
+    }
+
+    // segments that fit in each box
+    let limits = ORDER_BOXES.iter()
+        .map(|name| {
+            let b = layout_box(lb, name)?;
+            Ok(((b.2 - b.0) * (b.3 - b.1) / SEGMENT_AREA) as usize)
+        })
+        .collect::<Result<Vec<usize>,LSysError>>()?;
+    let mut counts = lsys_segment_counts(lsys, limits[3])?;
+    // only orders below those given
+    if let Some(&below) = lsys.order.first() {
+        counts.truncate(below.max(0) as usize);
+    }
+
+    let first  = counts.iter().position(|&c| c > 0).unwrap_or(0);
+    let right  = orders_pick(&counts, limits[2], first + 1);
+    let center = ((first + right) / 2).max(first + 1);
+    let right  = right.max(center + 1);
+    let main   = orders_pick(&counts, limits[3], right + 1);
+    let mut orders:Vec<i32> = [first,center,right,main].iter()
+        .map(|&o| o as i32)
+        .collect();
+    if lsys.order.is_empty() {
+        return Ok(orders);
+    }
+
+    // given orders go last, after the lowest of the others below them
+    let below = lsys.order[0];
+    orders.retain(|&o| o < below);
+    orders.dedup();
+    orders.truncate(n - lsys.order.len());
+    orders.extend(&lsys.order);
+    Ok(orders)
+}
+
+/*----------------------------------------------------------------------
+Draw one page from one LSys
+
+What could not be drawn is returned rather than reported here, so that
+the caller decides whether and how to tell anyone.  An order too large
+for its box leaves the box empty, and the rest of the page is drawn.
+*/
+
+/// Titles of LSys, with why they were not drawn, in whole or in part
+pub type Skipped = Vec<(String,LSysError)>;
+
+/// Draw lsys on the current page of the document
+pub fn lsys_draw_page(lsys:&LSys,ds:& mut DocState) -> Result<Skipped,LSysError> {
+    let lb = layout_boxes_make();
+
+    // show layout boxes or not
+    if false {
+        let mut svg_lb = layout_boxes_draw(&lb);
+        let lb_comment = indoc! {r#"
+            <!-- layout boxes -->
+            "#};
+        svg_lb.insert_str(0, lb_comment);
+        doc(ds, DocAct::PageAddFragment(&svg_lb))?;
+    }
+
+    // the orders fill the last boxes, any before them stay empty
+    let orders = lsys_orders(lsys, &lb)?;
+    let empty = ORDER_BOXES.len() - orders.len();
+    let mut skipped = vec!();
+    for (ibox,&order) in ORDER_BOXES[empty..].iter().zip(&orders) {
+        // a box too full to draw is left empty, the page is still useful
+        match lsys_draw_order_in_box(lsys, ds, &lb, order, ibox) {
+            Err(why @ (LSysError::OrderTooHigh { .. }
+                      | LSysError::TooManySegments { .. }
+                      | LSysError::TooManyModules { .. })) => {
+                skipped.push((lsys.title.clone(), why));
+            }
+            r => r?,
+        }
+    }
+
+    // draw title
+    let td = TextDesc {
+        size   : 30.0,
+        family : "serif",
+        weight : "bold",
+        anchor : "middle",
+        link   : false,
+    };
+    let top = layout_box(&lb, "top")?;
+    let mut svg = String::new();
+    svg.push_str(&doc_comment(ds, "title"));
+    let xmid = (top.2 + top.0)/2.0;
+    let lines = vec![lsys.title.clone()];
+    doc_text_lines(ds, &mut svg, xmid, top.1, &td, &lines)?;
+
+    // draw references
+    let td = TextDesc {
+        size   : 16.0,
+        family : "sans-serif",
+        weight : "normal",
+        anchor : "start",
+        link   : true,
+    };
+    svg.push_str(&doc_comment(ds, "references"));
+    let x = top.0 + 20.0;
+    let y = top.1 + 50.0;
+    doc_text_lines(ds, &mut svg, x, y, &td, &lsys.refs)?;
+
+    // draw angle and order
+    let td = TextDesc {
+        size   : 16.0,
+        family : "monospace",
+        weight : "normal",
+        anchor : "start",
+        link   : false,
+    };
+    svg.push_str(&doc_comment(ds, "angle order"));
+    let a = layout_box(&lb, "a")?;
+    let x = a.0 + 20.0;
+    let y = a.1 + 10.0;
+    let line1 = format!("Angle : {angle:.1}", angle = lsys.angle);
+    let auto = if lsys.order.len() < ORDER_BOXES.len() { " auto" } else { "" };
+    let line2 = format!("Order : {orders:?}{auto}");
+    let mut lines = vec![line1,line2];
+    if lsys_is_stochastic(lsys) {
+        lines.push(format!("Seed  : {seed}", seed = lsys.seed));
+    }
+    if !lsys.ignore.is_empty() {
+        lines.push(format!("Ignore: {ignore}", ignore = lsys.ignore));
+    }
+    if !lsys.colors.is_empty() {
+        lines.push(format!("Colors: {colors}", colors = lsys.colors.join(" ")));
+    }
+    if !lsys.draws.is_empty() || !lsys.moves.is_empty() {
+        lines.push(format!("Draws : {draws}  Moves: {moves}",
+            draws = lsys.draws, moves = lsys.moves));
+    }
+    if let Ok(growth) = growth::growth_new(lsys) {
+        let rate = growth::growth_rate(lsys, &growth);
+        lines.push(format!("Growth: {rate:.3}"));
+    }
+    if lsys.length_factor != 0.0 {
+        lines.push(format!("Factor: {factor}", factor = lsys.length_factor));
+    }
+    if lsys.width_factor != 0.0 {
+        lines.push(format!("Widths: {factor}", factor = lsys.width_factor));
+    }
+    if !lsys.schedule.is_empty() {
+        let sched:Vec<String> = lsys.schedule.iter()
+            .map(|(name,steps)| format!("{name} x{steps}"))
+            .collect();
+        lines.push(format!("Sched : {sched}", sched = sched.join(", ")));
+    }
+    let mut consts:Vec<(&String,&f64)> = lsys.consts.iter().collect();
+    consts.sort_by(|a,b| a.0.cmp(b.0));
+    for (k,v) in consts {
+        lines.push(format!("Const : {k} = {v}", k = k, v = v));
+    }
+    doc_text_lines(ds, &mut svg, x, y, &td, &lines)?;
+
+    // draw rules
+    svg.push_str(&doc_comment(ds, "rules"));
+    let b = layout_box(&lb, "b")?;
+    let x = b.0 + 20.0;
+    let y = b.1 + 0.0;
+    let mut lines = vec![];
+    let line = format!("Start : {start}", start = &lsys.start);
+    lines.push(line);
+    rules_text_lines(&lsys.rules, &mut lines);
+    let mut tables:Vec<(&String,&Rules)> = lsys.tables.iter().collect();
+    tables.sort_by(|a,b| a.0.cmp(b.0));
+    for (name,rules) in tables {
+        lines.push(format!("Table : {name}", name = name));
+        rules_text_lines(rules, &mut lines);
+    }
+    doc_text_lines(ds, &mut svg, x, y, &td, &lines)?;
+
+    // text may be anywhere on the page
+    doc(ds, DocAct::PageAddBBox((0.0, 0.0, PAGE_WIDTH, PAGE_HEIGHT)))?;
+    doc(ds, DocAct:: PageAddFragment(&svg))?;
+    Ok(skipped)
+}
+
+// one line of text for each rule, or each alternative of a rule
+fn rules_text_lines(rules:&Rules, lines:&mut Vec<String>) {
+    for (k,v) in rules {
+        match v {
+            Successor::Basic(v) => {
+                let line = format!("{k} : {v}", k = k, v = v);
+                lines.push(line);
+            }
+            Successor::Stochastic(alts) => {
+                for (w,v) in alts {
+                    let line = format!("{k} : ({w:.2}) {v}", k = k, w = w, v = v);
+                    lines.push(line);
+                }
+            }
+        }
+    }
+}
+
+/*
+An order is refused before it is derived when it is above ORDER_MAX,
+would draw more than SEGMENTS_MAX segments, or would derive more than
+MODULES_MAX modules.  D0L systems are predicted from their growth
+matrices.  Others are derived once and counted, stopping at the limit,
+which bounds the time taken for all but those which grow without
+giving the turtle anything to do.
+*/
+/// Refuse an order which is too high or too big to draw
+pub fn lsys_order_check(lsys:&LSys, order:i32) -> Result<(),LSysError> {
+    if order > ORDER_MAX {
+        return Err(LSysError::OrderTooHigh { order });
+    }
+    order_check_within(lsys, order, SEGMENTS_MAX, MODULES_MAX)
+}
+
+// the size limits of lsys_order_check, apart so tests can use smaller ones
+fn order_check_within(lsys:&LSys, order:i32, segments_max:u128, modules_max:u128)
+    -> Result<(),LSysError>
+{
+    if let Some(segments) = growth::lsys_segments_predict(lsys, order) {
+        if segments > segments_max {
+            return Err(LSysError::TooManySegments { order, segments });
+        }
+        let modules = growth::lsys_derived_length(lsys, order)?;
+        if modules > modules_max {
+            return Err(LSysError::TooManyModules { order, modules });
+        }
+        return Ok(());
+    }
+    let acts = lsys_actions(lsys);
+    let (mut segments, mut modules) = (0, 0);
+    for m in lsys_derive(lsys, order)? {
+        if actions_is_draw(&acts, &m.sym) {
+            segments += 1;
+        }
+        modules += 1;
+        if segments > segments_max {
+            return Err(LSysError::TooManySegments { order, segments });
+        }
+        if modules > modules_max {
+            return Err(LSysError::TooManyModules { order, modules });
+        }
+    }
+    Ok(())
+}
+
+fn lsys_draw_order_in_box(
+    lsys:&LSys,
+    ds:& mut DocState,
+    lb:&LayoutBoxes,
+    order:i32,
+    ibox:&str            ) -> Result<(),LSysError>
+{
+
+    //println!("{lsys:#?}");
+    lsys_order_check(lsys, order)?;
+    let pbb = layout_box(lb, ibox)?;
+    let (mut frag, drawn) = match ds.kind {
+        DocKind::Ps | DocKind::Eps | DocKind::Pdf => {
+            ps::ps_draw_basic(lsys, order, pbb, ds.kind == DocKind::Pdf)?
+        }
+        DocKind::Png => (String::new(), png::png_draw_basic(doc_raster(ds)?, lsys, order, pbb)?),
+        DocKind::Html | DocKind::Svg => (lsys_draw_basic(lsys, order, pbb)?, *pbb),
+    };
+    let comment = doc_comment(ds, &format!("box:{ibox} order:{order}"));
+    frag.insert_str(0, &comment);
+    doc(ds, DocAct::PageAddBBox(drawn))?;
+    doc(ds, DocAct::PageAddFragment(&frag))
+}
+
+/*----------------------------------------------------------------------
+Draw one figure from one LSys
+
+Only the figure, without title, rules or other orders, filling the
+page inside its margins.  The order is the one the main box of a page
+would show, so the last order given, or else the one chosen for it.
+*/
+
+/// The order drawn in the main box
+pub fn lsys_figure_order(lsys:&LSys) -> Result<i32,LSysError> {
+    let orders = lsys_orders(lsys, &layout_boxes_make())?;
+    orders.last().copied().ok_or_else(|| LSysError::MissingOrder("main".to_string()))
+}
+
+/// A figure is all there is of its page, so is never skipped in part
+pub fn lsys_draw_figure(lsys:&LSys, ds:&mut DocState) -> Result<Skipped,LSysError> {
+    let lb = layout_boxes_make();
+    let (top,main) = (layout_box(&lb, "top")?, layout_box(&lb, "main")?);
+    let figure = HashMap::from([("figure", (top.0, top.1, main.2, main.3))]);
+    lsys_draw_order_in_box(lsys, ds, &figure, lsys_figure_order(lsys)?, "figure")?;
+    Ok(vec!())
+}
+
+/*----------------------------------------------------------------------
+Standalone svg figures
+
+One figure at one order as a file of its own, to put in slides and
+other documents.  There is no page: the viewBox is the box the drawing
+covers, with room for the width of its lines, and the longer side is
+FIGURE_SIZE pixels.  The title and a description, giving the order,
+the axiom, the angle, the rules and the references, are kept as
+metadata.
+*/
+
+/// A standalone svg of lsys at order
+pub fn lsys_svg_figure(lsys:&LSys, order:i32) -> Result<String,LSysError> {
+    lsys_order_check(lsys, order)?;
+    let mut widest = 1.0f64;
+    let abb = lsys_dacts(lsys, lsys_derive(lsys,order)?, &mut |dact| {
+        if let DAct::Style(_,w) = dact {
+            widest = widest.max(w);
+        }
+    })?;
+
+    // a box in which the drawing fits with its longer side FIGURE_SIZE
+    let step = FIGURE_SIZE / BOX_USAGE_FRACTION / f64::max(abb.2 - abb.0, abb.3 - abb.1);
+    let pbb = (0.0, 0.0, (abb.2 - abb.0) * step, (abb.3 - abb.1) * step);
+    let (_, _, _, drawn) = lsys_fit(&abb, &pbb);
+    let pad = STROKE_WIDTH * widest / 2.0;
+    let (x, y) = (drawn.0 - pad, drawn.1 - pad);
+    let (width, height) = (drawn.2 - drawn.0 + 2.0 * pad, drawn.3 - drawn.1 + 2.0 * pad);
+
+    let mut desc = vec![
+        format!("Order {order} of a Lindenmayer system"),
+        format!("Start : {start}", start = lsys.start),
+        format!("Angle : {angle}", angle = lsys.angle),
+    ];
+    // sorted, so that the same figure gives the same file
+    let mut rules = vec!();
+    rules_text_lines(&lsys.rules, &mut rules);
+    rules.sort();
+    desc.extend(rules);
+    desc.extend(lsys.refs.iter().cloned());
+    let desc:Vec<String> = desc.iter().map(|line| xml_escape(line)).collect();
+
+    Ok(format!( indoc! {r#"
+        <?xml version="1.0" encoding="UTF-8" standalone="no"?>
+        <svg{ns}
+            width="{width:.2}"
+            height="{height:.2}"
+            viewBox="{x:.2} {y:.2} {width:.2} {height:.2}"
+        >
+        <title>{title}</title>
+        <desc>
+        {desc}
+        </desc>
+        {path}</svg>
+        "#},
+        ns = SVG_NAMESPACES,
+        width = width, height = height, x = x, y = y,
+        title = xml_escape(&lsys.title),
+        desc = desc.join("\n"),
+        path = lsys_draw_basic(lsys, order, &pbb)?,
+    ))
+}
+
+/*---------------------------------------------------------------------
+Json file handling
+
+A json file holds any number of LSys, each a json object.  They may be
+in a json array, one per line as in JSON Lines, or simply one after
+another, with or without white space between.  Lines starting with "#"
+are comments.  Any other text outside the objects is ignored with a
+warning, so that nothing disappears without notice.
+
+Each value is first read as plain json, to find where it ends, and
+then again as an LSys, so that an object which is not a valid LSys can
+be reported and skipped without losing the rest.  After a syntax error
+reading resumes at the next line starting with "{" or "[".  Problems
+are reported with the line and column where they are found, counting
+from one.
+*/
+
+/// A problem found loading a file, json or text
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoadNote {
+    /// From one
+    pub line:   usize,
+    /// From one, in characters
+    pub column: usize,
+    /// An LSys was lost, otherwise a warning
+    pub error:  bool,
+    /// What was found
+    pub text:   String,
+    /// File name, empty when parsed from a string
+    pub source: String,
+}
+
+impl std::fmt::Display for LoadNote {
+    fn fmt(&self, f:&mut std::fmt::Formatter) -> std::fmt::Result {
+        let kind = if self.error { "error" } else { "warning" };
+        if !self.source.is_empty() {
+            write!(f, "{}:", self.source)?;
+        }
+        write!(f, "{}:{}: {kind}: {}", self.line, self.column, self.text)
+    }
+}
+
+struct JsonReader<'a> {
+    json:  &'a str,
+    pos:   usize,               // byte offset of the next thing to read
+    lsysv: Vec<LSys>,
+    notes: Vec<LoadNote>,
+}
+
+// line and column of a byte offset
+fn json_position(json:&str, offset:usize) -> (usize,usize) {
+    let before = &json[..offset];
+    let line = before.matches('\n').count() + 1;
+    let column = before.rsplit('\n').next().map_or(0, |s| s.chars().count()) + 1;
+    (line,column)
+}
+
+fn json_note(r:&mut JsonReader, offset:usize, error:bool, text:String) {
+    let (line,column) = json_position(r.json, offset);
+    r.notes.push(LoadNote { line, column, error, text, source: String::new() });
+}
+
+// byte offset, from the start of src, of the place a serde error is at,
+// whose column counts bytes, back to the start of the character it is in
+fn json_error_offset(src:&str, why:&serde_json::Error) -> usize {
+    let mut offset = 0;
+    for (i,line) in src.split_inclusive('\n').enumerate() {
+        if i + 1 == why.line() {
+            let mut column = why.column().saturating_sub(1).min(line.len());
+            while !line.is_char_boundary(column) {
+                column -= 1;
+            }
+            return offset + column;
+        }
+        offset += line.len();
+    }
+    src.len()
+}
+
+// the reason of a serde error, without its position within src
+fn json_error_text(why:&serde_json::Error) -> String {
+    let text = why.to_string();
+    let suffix = format!(" at line {} column {}", why.line(), why.column());
+    text.strip_suffix(&suffix).unwrap_or(&text).to_string()
+}
+
+fn json_skip_space(r:&mut JsonReader) {
+    let rest = &r.json[r.pos..];
+    r.pos += rest.len() - rest.trim_start().len();
+}
+
+fn json_skip_line(r:&mut JsonReader) {
+    r.pos = r.json[r.pos..].find('\n').map_or(r.json.len(), |k| r.pos + k + 1);
+}
+
+// after a syntax error, go on at the next line starting with "{" or "["
+fn json_resync(r:&mut JsonReader) {
+    json_skip_line(r);
+    while r.pos < r.json.len() && !r.json[r.pos..].starts_with(['{', '[']) {
+        json_skip_line(r);
+    }
+}
+
+// one json value, or None after a syntax error, which is noted
+fn json_value(r:&mut JsonReader) -> Option<serde_json::Value> {
+    let src = &r.json[r.pos..];
+    let mut stream = serde_json::Deserializer::from_str(src).into_iter::<serde_json::Value>();
+    match stream.next()? {
+        Ok(value) => {
+            r.pos += stream.byte_offset();
+            Some(value)
+        }
+        Err(why) => {
+            let at = r.pos + json_error_offset(src, &why);
+            json_note(r, at, true, json_error_text(&why));
+            None
+        }
+    }
+}
+
+// an object, already read as a value, read again as an LSys
+fn json_lsys(r:&mut JsonReader, start:usize) {
+    let src = &r.json[start..r.pos];
+    match serde_json::from_str::<LSys>(src) {
+        Err(why) => {
+            let at = start + json_error_offset(src, &why);
+            json_note(r, at, true, json_error_text(&why));
+        }
+        Ok(lsys) => match lsys_check(&lsys) {
+            Err(why) => json_note(r, start, true, format!("'{}': {why}", lsys.title)),
+            Ok(()) => r.lsysv.push(lsys),
+        }
+    }
+}
+
+// the elements of an array, at its "["
+fn json_array(r:&mut JsonReader) {
+    r.pos += 1;
+    loop {
+        json_skip_space(r);
+        if r.json[r.pos..].starts_with(']') {
+            r.pos += 1;
+            return;
+        }
+        let start = r.pos;
+        match json_value(r) {
+            None => return json_resync(r),
+            Some(serde_json::Value::Object(_)) => json_lsys(r, start),
+            Some(_) => json_note(r, start, false,
+                "Ignored array element which is not an object".to_string()),
+        }
+        json_skip_space(r);
+        if r.json[r.pos..].starts_with(',') {
+            r.pos += 1;
+        }
+        else if !r.json[r.pos..].starts_with(']') {
+            json_note(r, r.pos, true, "Expected ',' or ']' in array".to_string());
+            return json_resync(r);
+        }
+    }
+}
+
+/// The LSys values of json, and notes on what was skipped
+pub fn lsys_json_parse(json:&str) -> (Vec<LSys>,Vec<LoadNote>) {
+    let mut r = JsonReader { json, pos: 0, lsysv: vec!(), notes: vec!() };
+    loop {
+        json_skip_space(&mut r);
+        let rest = &r.json[r.pos..];
+        match rest.chars().next() {
+            None => break,
+            Some('#') => json_skip_line(&mut r),
+            Some('[') => json_array(&mut r),
+            Some('{') => {
+                let start = r.pos;
+                match json_value(&mut r) {
+                    Some(_) => json_lsys(&mut r, start),
+                    None => json_resync(&mut r),
+                }
+            }
+            Some(_) => {
+                let line = rest.lines().next().unwrap_or("");
+                let (at,text) = (r.pos, format!("Ignored text '{}'", line.trim_end()));
+                json_note(&mut r, at, false, text);
+                json_skip_line(&mut r);
+            }
+        }
+    }
+    (r.lsysv, r.notes)
+}
+
+// what was loaded, with the notes marked as from the named source
+pub(crate) fn load_named(name:&str, (lsysv,mut notes):(Vec<LSys>,Vec<LoadNote>))
+    -> (Vec<LSys>,Vec<LoadNote>)
+{
+    for note in &mut notes {
+        note.source = name.to_string();
+    }
+    (lsysv, notes)
+}
+
+/// Load lsys from json, with problems noted against the name of the source
+pub fn lsys_from_json(name:&str, json:&str) -> (Vec<LSys>,Vec<LoadNote>) {
+    load_named(name, lsys_json_parse(json))
+}
+
+/*----------------------------------------------------------------------
+Draw Text lines
+*/
+
+struct TextDesc<'a> {
+    size:f64,          // pixels
+    family:&'a str,    // serif, sans-serif, monospace
+    weight:&'a str ,   // lighter, normal, bold, bolder
+    anchor:&'a str,    // start middle end
+    link: bool,        // this text is a link
+}
+
+// text lines, in the language of the document, pdf adding its links to the page
+fn doc_text_lines(
+    ds:&mut DocState,
+    out:&mut String,
+    x:f64,
+    y:f64,
+    td:&TextDesc,
+    lines:&Vec<String>
+) -> Result<(),LSysError> {
+    match ds.kind {
+        DocKind::Ps | DocKind::Eps => {
+            ps::ps_text_lines(out, x, y, td, lines, ds.kind == DocKind::Ps);
+        }
+        DocKind::Pdf => {
+            for (bb,uri) in pdf::pdf_text_lines(out, x, y, td, lines) {
+                doc(ds, DocAct::PageAddLink(bb, &uri))?;
+            }
+        }
+        DocKind::Png => png::png_text_lines(doc_raster(ds)?, x, y, td, lines),
+        DocKind::Html | DocKind::Svg => svg_draw_text_lines(out, x, y, td, lines),
+    }
+    Ok(())
+}
+
+fn svg_draw_text_lines(
+    svg: &mut String,
+    x:f64,
+    y:f64,
+    td:&TextDesc,
+    lines:&Vec<String>
+) {
+    svg.push_str( &format!( indoc! {r#"
+        <text
+            x="{x:.2}" y="{y:.2}" font-size="{size:.1}px"
+            font-family="{family}" font-weight="{weight}"
+            text-anchor="{anchor}"
+        >
+        "#},
+        x = x, y = y, size = td.size,
+        family = td.family, weight = td.weight, anchor=td.anchor
+    ));
+    for line in lines {
+        if td.link {
+            svg.push_str( &format!( indoc! {r#"
+                <a xlink:href="{line}">
+                "#},
+                line = xml_escape(line)
+            ));
+        }
+        svg.push_str( &format!( indoc! {r#"
+            <tspan x="{x:.2}" dy="1.2em">{line}</tspan>
+            "#},
+            x = x, line = xml_escape(line),
+        ));
+        if td.link {
+            svg.push_str("</a>\n");
+        }
+    }
+    svg.push_str("</text>\n");
+}
+
+/*----------------------------------------------------------------------
+Tune-able parameters
+*/
+
+static STROKE_WIDTH:f64       =  1.5;                    // pixels
+static PIXEL_PER_INCH:f64     = 96.0;                    // pixel/inch
+static PAGE_WIDTH:f64         =  8.5 * PIXEL_PER_INCH;   // pixels
+static PAGE_HEIGHT:f64        = 11.0 * PIXEL_PER_INCH;   // pixels
+static BOX_USAGE_FRACTION:f64 =  0.90;                   // dimensionless
+static FIGURE_SIZE:f64        = 480.0;                   // pixels, standalone
+static BOX_RADIUS:f64         = 10.0;                    // pixels
+static SEGMENT_AREA:f64       = 100.0;                   // pixels², per line
+static WIDTH_FACTOR:f64       =  0.7;                    // for "!", unless given
+static AUTO_ORDER_MAX:i32     = 24;                      // highest auto order
+static AUTO_MODULES_MAX:usize = 1_000_000;               // longest auto string
+static SEGMENTS_MAX:u128      = 2_000_000;               // most drawn in a box
+static MODULES_MAX:u128       = 20_000_000;              // most derived for a box
+/// Highest order drawn
+pub static ORDER_MAX:i32      = 100;
+static DEFAULT_COLORS:[&str;6] = [                      // svg colors
+    "black", "forestgreen", "saddlebrown", "gold", "crimson", "royalblue",
+];
+
+/*
+This keeps rotation always counter clockwise for consistent
+presentation of figures.  The turtle works with the y axis pointing
+up, and the y of every drawing action is multiplied by this.
+For postscript, which has y axis pointing up, set to +1.0.
+For svg/html, which has y axis pointing down, set to -1.0.
+The PostScript output turns its pages upside down to match svg, see
+ps.rs, so that both share the layout and this stays at -1.0.
+*/
+static ROTATION:f64           = -1.0;                     // dimensionless
+
+// for an svg document on its own
+static SVG_NAMESPACES:&str = concat!(
+    "\n    xmlns=\"http://www.w3.org/2000/svg\"",
+    "\n    xmlns:xlink=\"http://www.w3.org/1999/xlink\"",
+);
+
+/*----------------------------------------------------------------------
+Top level
+
+Write an HTML document with a page for each LSys.  One that can not be
+drawn is left out, and the rest still go in, and what was left out is
+returned.  Only errors in the document itself, such as IO, stop it.
+*/
+
+/// Write an html document of lsysv to path
+pub fn lsys_draw_doc(lsysv:&[LSys], path:&str, title:&str) -> Result<Skipped,LSysError> {
+    let ds = &mut doc_new();
+    doc(ds, DocAct::DocOpenPathTitle(path, title))?;
+    lsys_draw_pages(lsysv, ds, lsys_draw_page)
+}
+
+/// A page for each LSys of an open document, drawn by draw, then close it
+pub fn lsys_draw_pages(
+    lsysv:&[LSys],
+    ds:&mut DocState,
+    draw:fn(&LSys,&mut DocState) -> Result<Skipped,LSysError>,
+) -> Result<Skipped,LSysError> {
+    let mut skipped = vec!();
+    for lsys in lsysv {
+        doc(ds, DocAct::PageStartComment(&lsys.title))?;
+        match draw(lsys,ds) {
+            Ok(mut part) => {
+                skipped.append(&mut part);
+                doc(ds, DocAct::PageEnd)?;
+            }
+            Err(LSysError::Io(path,why)) => return Err(LSysError::Io(path,why)),
+            Err(why) => {
+                skipped.push((lsys.title.clone(), why));
+                doc(ds, DocAct::PageDiscard)?;
+            }
+        }
+    }
+    doc(ds, DocAct::DocClose)?;
+    Ok(skipped)
+}
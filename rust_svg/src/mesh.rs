@@ -0,0 +1,182 @@
+use std::f64::consts::PI;
+
+use super::*;
+
+/*----------------------------------------------------------------------
+3D output
+
+The turtle path can be written out in three dimensions, for viewing or
+for 3D printing.  Lines can go to a Wavefront OBJ file as they are.
+For STL, which only knows triangles, and optionally for OBJ, each line
+is made into a tube, a prism of a few sides with closed ends.
+
+Coordinates are in turtle steps, with the turtle starting at the
+origin heading along x.  Tubes are lengthened by their radius at both
+ends, so that they overlap where lines meet at an angle.
+*/
+
+/// Connected runs of lines, starting after each move
+pub fn lsys_polylines_3d(lsys:&LSys, order:i32) -> Result<Vec<Vec<V3>>,LSysError> {
+    lsys_order_check(lsys, order)?;
+    let mut lines:Vec<Vec<V3>> = vec!();
+    let mut run:Vec<V3> = vec![[0.0, 0.0, 0.0]];
+    turtle_run(lsys, lsys_derive(lsys,order)?, &mut |tact| {
+        match tact {
+            TAct::MoveTo(p) => {
+                if run.len() > 1 {
+                    lines.push(run.clone());
+                }
+                run = vec![p];
+            }
+            TAct::LineTo(p) => run.push(p),
+            // polygons have no thickness, so nothing to print,
+            // and tubes all have the one radius
+            TAct::Polygon(_) | TAct::Style(..) => (),
+        }
+    })?;
+    if run.len() > 1 {
+        lines.push(run);
+    }
+    Ok(lines)
+}
+
+/// Triangles, with shared vertices
+pub struct Mesh {
+    /// Shared by the triangles
+    pub vertices:  Vec<V3>,
+    /// Counter clockwise seen from outside
+    pub triangles: Vec<[usize;3]>,
+}
+
+/// Tubes of radius, with sides, around each line of lines
+pub fn mesh_tubes(lines:&[Vec<V3>], radius:f64, sides:usize) -> Mesh {
+    let mut mesh = Mesh { vertices: vec!(), triangles: vec!() };
+    let sides = sides.max(3);
+    for run in lines {
+        for pair in run.windows(2) {
+            let axis = v3_sub(pair[1], pair[0]);
+            let length = v3_len(axis);
+            if length < 1e-9 {
+                continue;
+            }
+            let d = v3_scale(axis, 1.0/length);
+            let a = v3_sub(pair[0], v3_scale(d, radius));
+            let b = v3_add(pair[1], v3_scale(d, radius));
+
+            // two unit vectors across the axis
+            let e = if d[0].abs() < 0.9 { [1.0, 0.0, 0.0] } else { [0.0, 1.0, 0.0] };
+            let p = v3_cross(d, e);
+            let p = v3_scale(p, 1.0/v3_len(p));
+            let q = v3_cross(d, p);
+
+            // end centres, then a ring of vertices at each end
+            let base = mesh.vertices.len();
+            mesh.vertices.push(a);
+            mesh.vertices.push(b);
+            for i in 0..sides {
+                let (s,c) = (2.0 * PI * i as f64 / sides as f64).sin_cos();
+                let r = v3_add(v3_scale(p, c * radius), v3_scale(q, s * radius));
+                mesh.vertices.push(v3_add(a, r));
+                mesh.vertices.push(v3_add(b, r));
+            }
+            let ring_a = |i:usize| base + 2 + 2 * (i % sides);
+            let ring_b = |i:usize| base + 3 + 2 * (i % sides);
+            for i in 0..sides {
+                mesh.triangles.push([ring_a(i), ring_a(i+1), ring_b(i+1)]);
+                mesh.triangles.push([ring_a(i), ring_b(i+1), ring_b(i)]);
+                mesh.triangles.push([base,      ring_a(i+1), ring_a(i)]);
+                mesh.triangles.push([base + 1,  ring_b(i),   ring_b(i+1)]);
+            }
+        }
+    }
+    mesh
+}
+
+/*----------------------------------------------------------------------
+Wavefront OBJ
+
+Plain text, with vertices numbered from one.
+*/
+
+/// An OBJ of lines
+pub fn obj_from_polylines(name:&str, lines:&[Vec<V3>]) -> String {
+    let mut obj = format!("# {name}\no lsys\n");
+    let mut n = 0;
+    for run in lines {
+        for v in run {
+            obj.push_str(&format!("v {:.6} {:.6} {:.6}\n", v[0], v[1], v[2]));
+        }
+        let ids:Vec<String> = (n+1..=n+run.len()).map(|i| i.to_string()).collect();
+        obj.push_str(&format!("l {}\n", ids.join(" ")));
+        n += run.len();
+    }
+    obj
+}
+
+/// An OBJ of mesh
+pub fn obj_from_mesh(name:&str, mesh:&Mesh) -> String {
+    let mut obj = format!("# {name}\no lsys\n");
+    for v in &mesh.vertices {
+        obj.push_str(&format!("v {:.6} {:.6} {:.6}\n", v[0], v[1], v[2]));
+    }
+    for t in &mesh.triangles {
+        obj.push_str(&format!("f {} {} {}\n", t[0]+1, t[1]+1, t[2]+1));
+    }
+    obj
+}
+
+/*----------------------------------------------------------------------
+STL, in both the ASCII and the binary form
+
+Each triangle carries its own unit normal.
+*/
+
+fn mesh_normal(mesh:&Mesh, t:&[usize;3]) -> V3 {
+    let [a,b,c] = t.map(|i| mesh.vertices[i]);
+    let n = v3_cross(v3_sub(b,a), v3_sub(c,a));
+    let len = v3_len(n);
+    if len > 0.0 { v3_scale(n, 1.0/len) } else { n }
+}
+
+/// An ASCII STL of mesh
+pub fn stl_ascii_from_mesh(name:&str, mesh:&Mesh) -> String {
+    // the name must be a single word
+    let name:String = name.chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '_' })
+        .collect();
+    let mut stl = format!("solid {name}\n");
+    for t in &mesh.triangles {
+        let n = mesh_normal(mesh, t);
+        stl.push_str(&format!("  facet normal {:e} {:e} {:e}\n", n[0], n[1], n[2]));
+        stl.push_str("    outer loop\n");
+        for &i in t {
+            let v = mesh.vertices[i];
+            stl.push_str(&format!("      vertex {:e} {:e} {:e}\n", v[0], v[1], v[2]));
+        }
+        stl.push_str("    endloop\n");
+        stl.push_str("  endfacet\n");
+    }
+    stl.push_str(&format!("endsolid {name}\n"));
+    stl
+}
+
+/// A binary STL of mesh
+pub fn stl_binary_from_mesh(name:&str, mesh:&Mesh) -> Vec<u8> {
+    // 80 byte header, which must not start with "solid"
+    let mut header = format!("binary STL: {name}").into_bytes();
+    header.resize(80, b' ');
+    let mut stl = header;
+    stl.extend_from_slice(&(mesh.triangles.len() as u32).to_le_bytes());
+    for t in &mesh.triangles {
+        let n = mesh_normal(mesh, t);
+        let vs = t.map(|i| mesh.vertices[i]);
+        for v in [n, vs[0], vs[1], vs[2]] {
+            for x in v {
+                stl.extend_from_slice(&(x as f32).to_le_bytes());
+            }
+        }
+        // attribute byte count
+        stl.extend_from_slice(&[0, 0]);
+    }
+    stl
+}
@@ -0,0 +1,228 @@
+use super::*;
+
+/*----------------------------------------------------------------------
+PDF output
+
+A PDF document with a page for each LSys, as in the html, written
+without the help of any other program.  Pages are drawn as in ps.rs,
+in pixels with y down, by a transformation at the start of each page.
+PDF shares the PostScript names of the path operators, so figures are
+drawn by ps_draw_basic.
+
+Text uses three of the standard 14 fonts, which every PDF reader has,
+so nothing is embedded: Times-Bold for the title, Helvetica for the
+references and Courier for the rest, one for each family the pages
+use.  Widths of their characters are here, to center the title and to
+size the links.  References become link annotations with URI actions.
+
+The file is written a page at a time, as the other documents are.
+The offset of each object is kept for the cross reference table at the
+end.  Objects which every page refers to, the page tree and the fonts,
+have fixed numbers and are written last.  Page contents are compressed
+with zlib::zlib_compress, which pdf reads as /FlateDecode, so that the
+many numbers of a large figure take a fraction of the space.
+
+    https://opensource.adobe.com/dc-acrobat-sdk-docs/pdfstandards/PDF32000_2008.pdf
+*/
+
+// objects with fixed numbers, the fonts following in PDF_FONTS order
+static PDF_CATALOG:usize = 1;
+static PDF_PAGES:usize   = 2;
+static PDF_INFO:usize    = 3;
+static PDF_FONT:usize    = 4;
+static PDF_FREE:usize    = 7;   // first number for pages
+
+// family, font, and widths of ' ' to '~' in thousandths of the size,
+// from the Adobe font metrics, in WinAnsiEncoding
+static PDF_FONTS:[(&str,&str,[u16;95]);3] = [
+    ("serif", "Times-Bold", [
+        250, 333, 555, 500, 500,1000, 833, 278, 333, 333, 500, 570, 250, 333, 250, 278,
+        500, 500, 500, 500, 500, 500, 500, 500, 500, 500, 333, 333, 570, 570, 570, 500,
+        930, 722, 667, 722, 722, 667, 611, 778, 778, 389, 500, 778, 667, 944, 722, 778,
+        611, 778, 722, 556, 667, 722, 722,1000, 722, 722, 667, 333, 278, 333, 581, 500,
+        333, 500, 556, 444, 556, 444, 333, 500, 556, 278, 333, 556, 278, 833, 556, 500,
+        556, 556, 444, 389, 333, 556, 500, 722, 500, 500, 444, 394, 220, 394, 520,
+    ]),
+    ("sans-serif", "Helvetica", [
+        278, 278, 355, 556, 556, 889, 667, 191, 333, 333, 389, 584, 278, 333, 278, 278,
+        556, 556, 556, 556, 556, 556, 556, 556, 556, 556, 278, 278, 584, 584, 584, 556,
+       1015, 667, 667, 722, 722, 667, 611, 778, 722, 278, 500, 667, 556, 833, 722, 778,
+        667, 778, 722, 667, 611, 722, 667, 944, 667, 667, 611, 278, 278, 278, 469, 556,
+        333, 556, 556, 500, 556, 556, 278, 556, 556, 222, 222, 500, 222, 833, 556, 556,
+        556, 556, 333, 500, 278, 556, 500, 722, 500, 500, 500, 334, 260, 334, 584,
+    ]),
+    ("monospace", "Courier", [600; 95]),
+];
+
+// index into PDF_FONTS for the family of td, Helvetica if not known
+fn pdf_font(td:&TextDesc) -> usize {
+    PDF_FONTS.iter().position(|(family,_,_)| *family == td.family).unwrap_or(1)
+}
+
+// width of text in font, in units of its size, as ps_string writes it
+pub(crate) fn pdf_text_width(font:usize, text:&str) -> f64 {
+    let widths = &PDF_FONTS[font].2;
+    text.chars()
+        .map(|c| match c {
+            ' '..='~' => widths[c as usize - ' ' as usize],
+            _ => widths['?' as usize - ' ' as usize],
+        })
+        .map(|w| w as f64 / 1000.0)
+        .sum()
+}
+
+// write object n, noting where it starts
+fn pdf_object(ds:&mut DocState, n:usize, body:&[u8]) {
+    ds.objects[n] = ds.written + ds.buf.len();
+    ds.buf.extend_from_slice(format!("{n} 0 obj\n").as_bytes());
+    ds.buf.extend_from_slice(body);
+    ds.buf.extend_from_slice(b"\nendobj\n");
+}
+
+// a number for a new object, written later with pdf_object
+fn pdf_object_new(ds:&mut DocState) -> usize {
+    ds.objects.push(0);
+    ds.objects.len() - 1
+}
+
+pub(crate) fn pdf_doc_head(ds:&mut DocState) {
+    ds.objects = vec![0; PDF_FREE];
+    ds.kids.clear();
+    // the binary comment tells transfer programs that this is not text
+    ds.buf.extend_from_slice(b"%PDF-1.4\n%\xe2\xe3\xcf\xd3\n");
+}
+
+pub(crate) fn pdf_page_head(page_no:usize, comment:&str) -> String {
+    format!( indoc! {r#"
+        % begin page {page_no}
+        % {comment}
+        q
+        {scale} 0 0 -{scale} 0 {height} cm
+        4 M
+        "#},
+        page_no = page_no,
+        comment = comment.replace(['\n', '\r'], " "),
+        scale = ps::POINT_PER_PIXEL,
+        height = PAGE_HEIGHT * ps::POINT_PER_PIXEL,
+    )
+}
+
+/*
+The page started at ds.page_at, which is its content, as the objects
+of the page: its content stream, its links and itself.
+*/
+pub(crate) fn pdf_page_end(ds:&mut DocState) {
+    let mut content = ds.buf.split_off(ds.page_at);
+    content.extend_from_slice(b"\nQ\n");
+    let mut content = zlib::zlib_compress(&content);
+    let mut stream = format!("<< /Length {} /Filter /FlateDecode >>\nstream\n", content.len()).into_bytes();
+    stream.append(&mut content);
+    stream.extend_from_slice(b"endstream");
+    let contents = pdf_object_new(ds);
+    pdf_object(ds, contents, &stream);
+
+    let mut annots = vec!();
+    for (bb,uri) in std::mem::take(&mut ds.links) {
+        let (x0,y0) = ps::ps_point(bb.0, bb.3);
+        let (x1,y1) = ps::ps_point(bb.2, bb.1);
+        let link = format!( indoc! {r#"
+            << /Type /Annot /Subtype /Link
+               /Rect [{x0:.2} {y0:.2} {x1:.2} {y1:.2}]
+               /Border [0 0 0]
+               /A << /S /URI /URI {uri} >>
+            >>"#},
+            x0 = x0, y0 = y0, x1 = x1, y1 = y1,
+            uri = ps::ps_string(&uri),
+        );
+        let n = pdf_object_new(ds);
+        pdf_object(ds, n, link.as_bytes());
+        annots.push(format!("{n} 0 R"));
+    }
+
+    let fonts:Vec<String> = (0..PDF_FONTS.len())
+        .map(|i| format!("/F{} {} 0 R", i + 1, PDF_FONT + i))
+        .collect();
+    let (x1,y1) = ps::ps_point(PAGE_WIDTH, 0.0);
+    let page = format!( indoc! {r#"
+        << /Type /Page /Parent {parent} 0 R
+           /MediaBox [0 0 {x1} {y1}]
+           /Resources << /Font << {fonts} >> >>
+           /Contents {contents} 0 R
+           /Annots [{annots}]
+        >>"#},
+        parent = PDF_PAGES, x1 = x1, y1 = y1,
+        fonts = fonts.join(" "),
+        contents = contents,
+        annots = annots.join(" "),
+    );
+    let n = pdf_object_new(ds);
+    pdf_object(ds, n, page.as_bytes());
+    ds.kids.push(n);
+}
+
+// the objects every page refers to, the cross reference table and the trailer
+pub(crate) fn pdf_doc_foot(ds:&mut DocState) {
+    for (i,(_,font,_)) in PDF_FONTS.iter().enumerate() {
+        let body = format!(
+            "<< /Type /Font /Subtype /Type1 /BaseFont /{font} /Encoding /WinAnsiEncoding >>");
+        pdf_object(ds, PDF_FONT + i, body.as_bytes());
+    }
+    let info = format!("<< /Title {} /Producer (rust_svg) >>", ps::ps_string(&ds.title));
+    pdf_object(ds, PDF_INFO, info.as_bytes());
+    let kids:Vec<String> = ds.kids.iter().map(|n| format!("{n} 0 R")).collect();
+    let pages = format!("<< /Type /Pages /Kids [{}] /Count {} >>", kids.join(" "), kids.len());
+    pdf_object(ds, PDF_PAGES, pages.as_bytes());
+    let catalog = format!("<< /Type /Catalog /Pages {PDF_PAGES} 0 R >>");
+    pdf_object(ds, PDF_CATALOG, catalog.as_bytes());
+
+    // entries are exactly 20 bytes, object 0 heads the list of free ones
+    let xref = ds.written + ds.buf.len();
+    let mut foot = format!("xref\n0 {}\n0000000000 65535 f \n", ds.objects.len());
+    for offset in &ds.objects[1..] {
+        foot.push_str(&format!("{offset:010} 00000 n \n"));
+    }
+    foot.push_str(&format!( indoc! {r#"
+        trailer
+        << /Size {size} /Root {root} 0 R /Info {info} 0 R >>
+        startxref
+        {xref}
+        %%EOF
+        "#},
+        size = ds.objects.len(), root = PDF_CATALOG, info = PDF_INFO, xref = xref,
+    ));
+    ds.buf.extend_from_slice(foot.as_bytes());
+}
+
+/*
+Lines of text, as svg_draw_text_lines draws them, the first line a
+line height below y.  With td.link, the area of each line and the line
+itself, for links.
+*/
+pub(crate) fn pdf_text_lines(
+    out:&mut String,
+    x:f64,
+    y:f64,
+    td:&TextDesc,
+    lines:&[String],
+) -> Vec<(BBox,String)> {
+    let size = td.size;
+    let font = pdf_font(td);
+    let mut links = vec!();
+    out.push_str(&format!("BT\n/F{} {size} Tf\n", font + 1));
+    for (i,line) in lines.iter().enumerate() {
+        let width = pdf_text_width(font, line) * size;
+        let base = y + 1.2 * size * (i + 1) as f64;
+        let x0 = match td.anchor {
+            "middle" => x - width / 2.0,
+            "end"    => x - width,
+            _        => x,
+        };
+        // flipped back, as the page is
+        out.push_str(&format!("1 0 0 -1 {x0:.2} {base:.2} Tm {} Tj\n", ps::ps_string(line)));
+        if td.link {
+            links.push(((x0, base - size, x0 + width, base + 0.25 * size), line.clone()));
+        }
+    }
+    out.push_str("ET\n");
+    links
+}
@@ -0,0 +1,403 @@
+use std::collections::{HashMap, HashSet};
+
+use super::*;
+
+/*----------------------------------------------------------------------
+Pen plotters
+
+A figure as G-code, for plotters driven like milling machines, or as
+HPGL, the language of HP plotters and many cutters.  Both take the
+drawing actions of lsys_dacts: a line is drawn with the pen down, a
+move is made with the pen up.  Each color of the LSys is a pen, and a
+polygon, which a plotter can not fill, is drawn as its outline.
+
+The figure is fitted to an area of PlotOptions, in millimetres, with
+its lower left corner at the origin and y up, as plotters have it.
+
+A turtle drawing a branching plant draws each branch out to its tip,
+then moves back to where it branched, with the pen up.  So the paths,
+the runs of lines between moves, are put in a better order.  First
+they are split where others start or end, at the branches.  Then, with
+each pen in turn, the next path is the one with an end nearest to
+where the pen is, drawn backwards when that end is its last.  A path
+starting where the last one ended is drawn without lifting the pen, so
+a tree is drawn out to a tip, back down its neighbour, and on up the
+next branch.
+
+G-code is plain absolute moves, in mm, G0 with the pen up and G1 with
+it down.  Lifting and lowering the pen differ from machine to machine,
+so the commands for them are options, and so are the feed rates.  A
+change of pen is a pause, M0, with a comment saying which pen.
+
+HPGL has 40 plotter units to the mm, and a pen for each color.  Its
+speed is set with VS, in cm/s, from the drawing feed rate.  Plotters
+move with the pen up as fast as they can.
+
+    https://en.wikipedia.org/wiki/G-code
+    https://en.wikipedia.org/wiki/HP-GL
+*/
+
+/// How a figure is plotted, see plot_options_new for the defaults
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlotOptions {
+    /// Width in mm of the area the figure is fitted to
+    pub width:       f64,
+    /// Height in mm
+    pub height:      f64,
+    /// Speed in mm/minute with the pen down
+    pub draw_feed:   f64,
+    /// Speed in mm/minute with the pen up, G-code only
+    pub travel_feed: f64,
+    /// G-code to lift the pen
+    pub pen_up:      String,
+    /// G-code to lower the pen
+    pub pen_down:    String,
+    /// Paths ordered for less travel
+    pub reorder:     bool,
+}
+
+/// The default plot options, for A4
+pub fn plot_options_new() -> PlotOptions {
+    PlotOptions {
+        width:       180.0,     // A4, less 15 mm margins
+        height:      267.0,
+        draw_feed:   1000.0,
+        travel_feed: 3000.0,
+        pen_up:      "G0 Z5".to_string(),
+        pen_down:    "G0 Z0".to_string(),
+        reorder:     true,
+    }
+}
+
+/// Lines drawn without lifting the pen, in mm
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlotPath {
+    /// Index into the color map
+    pub pen:    usize,
+    /// Visited in order
+    pub points: Vec<(f64,f64)>,
+}
+
+fn plot_distance(a:(f64,f64), b:(f64,f64)) -> f64 {
+    (a.0 - b.0).hypot(a.1 - b.1)
+}
+
+// closer than this is the same place, mm
+static PLOT_SAME:f64 = 1e-3;
+
+/*
+The paths of lsys at order, fitted to the area of opts, in the order
+drawn or, with opts.reorder, in the order of plot_reorder.
+*/
+/// The pen paths of lsys at order
+pub fn lsys_plot_paths(lsys:&LSys, order:i32, opts:&PlotOptions) -> Result<Vec<PlotPath>,LSysError> {
+    lsys_order_check(lsys, order)?;
+    let mut paths:Vec<PlotPath> = vec!();
+    let mut run = PlotPath { pen: 0, points: vec![(0.0, 0.0)] };
+    let (mut x, mut y) = (0.0, 0.0);
+    let abb = lsys_dacts(lsys, lsys_derive(lsys,order)?, &mut |dact| {
+        match dact {
+            DAct::RmoveTo(xs,ys) => {
+                (x, y) = (x + xs, y + ys);
+                let last = std::mem::replace(&mut run.points, vec![(x, y)]);
+                if last.len() > 1 {
+                    paths.push(PlotPath { pen: run.pen, points: last });
+                }
+            }
+            DAct::RlineTo(xs,ys) => {
+                (x, y) = (x + xs, y + ys);
+                run.points.push((x, y));
+            }
+            DAct::Polygon(vs) => {
+                let mut points:Vec<(f64,f64)> = vs.iter().map(|(xs,ys)| (x + xs, y + ys)).collect();
+                if let Some(&first) = points.first() {
+                    points.push(first);
+                    paths.push(PlotPath { pen: run.pen, points });
+                }
+            }
+            DAct::Style(c,_) => {
+                let last = std::mem::replace(&mut run.points, vec![(x, y)]);
+                if last.len() > 1 {
+                    paths.push(PlotPath { pen: run.pen, points: last });
+                }
+                run.pen = c;
+            }
+        }
+    })?;
+    if run.points.len() > 1 {
+        paths.push(run);
+    }
+
+    // steps, with y down, to mm with y up
+    let scale = f64::min(opts.width / (abb.2 - abb.0), opts.height / (abb.3 - abb.1));
+    for path in &mut paths {
+        for p in &mut path.points {
+            *p = ((p.0 - abb.0) * scale, (abb.3 - p.1) * scale);
+        }
+    }
+    Ok(if opts.reorder { plot_reorder(paths) } else { paths })
+}
+
+/*
+The ends of paths, in square cells about as many as the ends, so that
+the nearest end is found by looking at the cells in rings around a
+place, without looking at them all.
+*/
+struct PlotGrid {
+    x0:    f64,
+    y0:    f64,
+    cell:  f64,
+    cols:  usize,
+    rows:  usize,
+    cells: Vec<Vec<(usize,bool)>>,      // path, and true for its last point
+}
+
+fn plot_grid_new(paths:&[PlotPath]) -> PlotGrid {
+    let ends:Vec<(f64,f64)> = paths.iter()
+        .flat_map(|p| [p.points[0], p.points[p.points.len() - 1]])
+        .collect();
+    let x0 = ends.iter().map(|p| p.0).fold(f64::MAX, f64::min);
+    let y0 = ends.iter().map(|p| p.1).fold(f64::MAX, f64::min);
+    let x1 = ends.iter().map(|p| p.0).fold(f64::MIN, f64::max);
+    let y1 = ends.iter().map(|p| p.1).fold(f64::MIN, f64::max);
+    let cell = (((x1 - x0) * (y1 - y0)) / ends.len() as f64).sqrt().max((x1 - x0 + y1 - y0) / 4096.0).max(PLOT_SAME);
+    let cols = ((x1 - x0) / cell) as usize + 1;
+    let rows = ((y1 - y0) / cell) as usize + 1;
+    let mut g = PlotGrid { x0, y0, cell, cols, rows, cells: vec![vec!(); cols * rows] };
+    for (k,end) in ends.iter().enumerate() {
+        let (i,j) = plot_grid_cell(&g, *end);
+        g.cells[j as usize * cols + i as usize].push((k / 2, k % 2 == 1));
+    }
+    g
+}
+
+// the cell of p, those outside the grid in the nearest one
+fn plot_grid_cell(g:&PlotGrid, p:(f64,f64)) -> (i64,i64) {
+    let i = ((p.0 - g.x0) / g.cell).floor().clamp(0.0, (g.cols - 1) as f64);
+    let j = ((p.1 - g.y0) / g.cell).floor().clamp(0.0, (g.rows - 1) as f64);
+    (i as i64, j as i64)
+}
+
+// path k, once drawn, out of the grid
+fn plot_grid_remove(g:&mut PlotGrid, paths:&[PlotPath], k:usize) {
+    let points = &paths[k].points;
+    for end in [points[0], points[points.len() - 1]] {
+        let (i,j) = plot_grid_cell(g, end);
+        g.cells[j as usize * g.cols + i as usize].retain(|e| e.0 != k);
+    }
+}
+
+// the path in the grid with an end nearest p, of those ends which fit,
+// and whether that is its last point
+fn plot_nearest(
+    g:&PlotGrid,
+    paths:&[PlotPath],
+    p:(f64,f64),
+    fit:&dyn Fn((f64,f64)) -> bool,
+) -> Option<(usize,bool)> {
+    let (ci,cj) = plot_grid_cell(g, p);
+    let mut best:Option<(usize,bool,f64)> = None;
+    for ring in 0..=(g.cols + g.rows) as i64 {
+        // every end in this ring, or beyond, is at least this far
+        if best.is_some_and(|b| b.2 <= (ring - 1) as f64 * g.cell) {
+            break;
+        }
+        let mut cells = vec!();
+        for i in ci - ring..=ci + ring {
+            cells.push((i, cj - ring));
+            if ring > 0 {
+                cells.push((i, cj + ring));
+            }
+        }
+        for j in cj - ring + 1..cj + ring {
+            cells.push((ci - ring, j));
+            cells.push((ci + ring, j));
+        }
+        for (i,j) in cells {
+            if i < 0 || j < 0 || i >= g.cols as i64 || j >= g.rows as i64 {
+                continue;
+            }
+            for &(k,last) in &g.cells[j as usize * g.cols + i as usize] {
+                let points = &paths[k].points;
+                let end = if last { points[points.len() - 1] } else { points[0] };
+                if !fit(end) {
+                    continue;
+                }
+                let d = plot_distance(p, end);
+                if best.is_none_or(|b| d < b.2) {
+                    best = Some((k, last, d));
+                }
+            }
+        }
+    }
+    best.map(|(k,last,_)| (k,last))
+}
+
+// a place, to PLOT_SAME
+fn plot_key(p:(f64,f64)) -> (i64,i64) {
+    ((p.0 / PLOT_SAME).round() as i64, (p.1 / PLOT_SAME).round() as i64)
+}
+
+// paths split at points where another starts or ends
+fn plot_split(paths:Vec<PlotPath>) -> Vec<PlotPath> {
+    let ends:HashSet<(i64,i64)> = paths.iter()
+        .flat_map(|p| [plot_key(p.points[0]), plot_key(p.points[p.points.len() - 1])])
+        .collect();
+    let mut split = vec!();
+    for path in paths {
+        let mut points = vec![path.points[0]];
+        for (i,&p) in path.points.iter().enumerate().skip(1) {
+            points.push(p);
+            if i + 1 < path.points.len() && ends.contains(&plot_key(p)) {
+                let piece = std::mem::replace(&mut points, vec![p]);
+                split.push(PlotPath { pen: path.pen, points: piece });
+            }
+        }
+        split.push(PlotPath { pen: path.pen, points });
+    }
+    split
+}
+
+/// Paths split and ordered to lift the pen less, and travel less with it lifted
+pub fn plot_reorder(paths:Vec<PlotPath>) -> Vec<PlotPath> {
+    let paths = plot_split(paths);
+    let mut pens:Vec<usize> = vec!();
+    for path in &paths {
+        if !pens.contains(&path.pen) {
+            pens.push(path.pen);
+        }
+    }
+    let mut ordered = Vec::with_capacity(paths.len());
+    let mut at = (0.0, 0.0);
+    for pen in pens {
+        let group:Vec<PlotPath> = paths.iter().filter(|p| p.pen == pen).cloned().collect();
+        let mut grid = plot_grid_new(&group);
+        // paths not yet drawn ending at each place, and the places with an odd number
+        let mut degree:HashMap<(i64,i64),usize> = HashMap::new();
+        for path in &group {
+            *degree.entry(plot_key(path.points[0])).or_default() += 1;
+            *degree.entry(plot_key(path.points[path.points.len() - 1])).or_default() += 1;
+        }
+        let mut odds = degree.values().filter(|&&d| d % 2 == 1).count();
+        loop {
+            // going on from where the pen is, or else starting where an odd
+            // number of paths end, as the pen must start or stop at each of those
+            let odd = |end| odds == 0
+                || plot_distance(at, end) <= PLOT_SAME
+                || degree[&plot_key(end)] % 2 == 1;
+            let Some((k,last)) = plot_nearest(&grid, &group, at, &odd) else {
+                break;
+            };
+            plot_grid_remove(&mut grid, &group, k);
+            for end in [group[k].points[0], group[k].points[group[k].points.len() - 1]] {
+                let d = degree.get_mut(&plot_key(end)).unwrap();
+                *d -= 1;
+                if *d % 2 == 1 { odds += 1 } else { odds -= 1 }
+            }
+            let mut path = group[k].clone();
+            if last {
+                path.points.reverse();
+            }
+            at = path.points[path.points.len() - 1];
+            ordered.push(path);
+        }
+    }
+    ordered
+}
+
+/// Distance moved with the pen up, from the origin through paths and back, in mm
+pub fn plot_travel(paths:&[PlotPath]) -> f64 {
+    let mut at = (0.0, 0.0);
+    let mut travel = 0.0;
+    for path in paths {
+        travel += plot_distance(at, path.points[0]);
+        at = path.points[path.points.len() - 1];
+    }
+    travel + plot_distance(at, (0.0, 0.0))
+}
+
+/// G-code to plot paths
+pub fn gcode_from_paths(name:&str, paths:&[PlotPath], opts:&PlotOptions) -> String {
+    let drawn:f64 = paths.iter()
+        .map(|p| p.points.windows(2).map(|w| plot_distance(w[0], w[1])).sum::<f64>())
+        .sum();
+    let mut gcode = format!( indoc! {r#"
+        ; {name}
+        ; {paths} paths, {drawn:.0} mm drawn, {travel:.0} mm with the pen up
+        G21 ; mm
+        G90 ; absolute
+        {pen_up}
+        "#},
+        name = name.replace(['\n', '\r'], " "),
+        paths = paths.len(),
+        drawn = drawn,
+        travel = plot_travel(paths),
+        pen_up = opts.pen_up,
+    );
+    let mut at = (0.0, 0.0);
+    let mut down = false;
+    let mut fed = false;
+    let mut pen = paths.first().map_or(0, |p| p.pen);
+    for path in paths {
+        let start = path.points[0];
+        if path.pen != pen || plot_distance(at, start) > PLOT_SAME {
+            if down {
+                gcode.push_str(&format!("{}\n", opts.pen_up));
+                down = false;
+            }
+            if path.pen != pen {
+                pen = path.pen;
+                gcode.push_str(&format!("M0 ; pen {}\n", pen + 1));
+            }
+            gcode.push_str(&format!("G0 X{:.3} Y{:.3} F{}\n", start.0, start.1, opts.travel_feed));
+            fed = false;
+        }
+        if !down {
+            gcode.push_str(&format!("{}\n", opts.pen_down));
+            down = true;
+        }
+        for p in &path.points[1..] {
+            // the feed rate is modal, so given once after each G0
+            let feed = if fed { String::new() } else { format!(" F{}", opts.draw_feed) };
+            gcode.push_str(&format!("G1 X{:.3} Y{:.3}{feed}\n", p.0, p.1));
+            fed = true;
+        }
+        at = path.points[path.points.len() - 1];
+    }
+    if down {
+        gcode.push_str(&format!("{}\n", opts.pen_up));
+    }
+    gcode.push_str(&format!("G0 X0 Y0 F{}\nM2\n", opts.travel_feed));
+    gcode
+}
+
+static HPGL_UNITS:f64 = 40.0;               // per mm
+static HPGL_POINTS:usize = 16;              // on one line of PD
+
+/// HP-GL to plot paths
+pub fn hpgl_from_paths(paths:&[PlotPath], opts:&PlotOptions) -> String {
+    let unit = |p:(f64,f64)| format!("{},{}", (p.0 * HPGL_UNITS).round(), (p.1 * HPGL_UNITS).round());
+    // cm/s, from mm/minute
+    let speed = (opts.draw_feed / 600.0).clamp(0.1, 128.0);
+    let mut hpgl = format!("IN;\nVS{speed:.1};\n");
+    let mut at = (0.0, 0.0);
+    let mut pen = None;
+    for path in paths {
+        let start = path.points[0];
+        if pen != Some(path.pen) {
+            pen = Some(path.pen);
+            hpgl.push_str(&format!("SP{};\n", path.pen + 1));
+            hpgl.push_str(&format!("PU{};\n", unit(start)));
+        }
+        else if plot_distance(at, start) > PLOT_SAME {
+            hpgl.push_str(&format!("PU{};\n", unit(start)));
+        }
+        for chunk in path.points[1..].chunks(HPGL_POINTS) {
+            let points:Vec<String> = chunk.iter().map(|p| unit(*p)).collect();
+            hpgl.push_str(&format!("PD{};\n", points.join(",")));
+        }
+        at = path.points[path.points.len() - 1];
+    }
+    hpgl.push_str("PU0,0;\nSP0;\n");
+    hpgl
+}
@@ -0,0 +1,439 @@
+use super::*;
+use super::color::{Rgb, color_rgb};
+
+/*----------------------------------------------------------------------
+PNG output
+
+Bitmaps of figures and pages, drawn here without a browser or any
+graphics library, so that they can be made anywhere, and compared.
+The same input always gives the same image.
+
+Drawing actions are drawn as they come from lsys_dacts, into a raster
+of the whole page, in 8 bit red, green, blue and alpha as the file has
+them, so that a page at 600 dpi takes 135 MB rather than four times
+that as floating point.  Each color is blended over what is there and
+rounded, which is close enough for the few layers a drawing has.  Lines
+are drawn with round ends, each pixel covered by how near its center is
+to the line, which smooths edges when anti-aliasing.  Polygons are
+filled after the lines, as in ps.rs, sampling 4 by 4 points in each
+pixel.  Text uses a small bitmap font, 5 by 7 dots, scaled to its size,
+which is enough to read, though not what a browser shows.
+
+The dpi of PngOptions sets the resolution, 96 giving a pixel of image
+for each pixel of the page.  A page with a figure alone is cropped to
+what is drawn, as eps is.
+
+The file is a single image in 8 bit RGBA, whose data is compressed by
+zlib.rs, as pdf page contents are.
+
+    https://www.w3.org/TR/png/
+*/
+
+/// How a png is drawn, see png_options_new for the defaults
+#[derive(Debug, Clone, PartialEq)]
+pub struct PngOptions {
+    /// Image pixels per inch of page
+    pub dpi:          f64,
+    /// Page pixels, for a line width of 1
+    pub stroke_width: f64,
+    /// None for transparent
+    pub background:   Option<Rgb>,
+    /// Smooth edges, with partial coverage
+    pub antialias:    bool,
+}
+
+/// The default png options
+pub fn png_options_new() -> PngOptions {
+    PngOptions {
+        dpi:          PIXEL_PER_INCH,
+        stroke_width: STROKE_WIDTH,
+        background:   Some([255, 255, 255]),
+        antialias:    true,
+    }
+}
+
+// the page being drawn
+pub(crate) struct Raster {
+    pub(crate) opts:   PngOptions,
+    pub(crate) scale:  f64,             // image pixels per page pixel
+    pub(crate) width:  usize,
+    pub(crate) height: usize,
+    pub(crate) pixels: Vec<[u8;4]>,     // not premultiplied
+}
+
+pub(crate) fn raster_new(opts:&PngOptions) -> Raster {
+    let scale = opts.dpi / PIXEL_PER_INCH;
+    let width = (PAGE_WIDTH * scale).ceil() as usize;
+    let height = (PAGE_HEIGHT * scale).ceil() as usize;
+    let clear = match opts.background {
+        Some([r,g,b]) => [r, g, b, 255],
+        None => [0; 4],
+    };
+    Raster { opts: opts.clone(), scale, width, height, pixels: vec![clear; width * height] }
+}
+
+// paint pixel (i,j) with color, alpha of the way
+fn raster_blend(r:&mut Raster, i:usize, j:usize, color:Rgb, alpha:f64) {
+    let alpha = alpha as f32;
+    let p = &mut r.pixels[j * r.width + i];
+    let under = p[3] as f32 / 255.0 * (1.0 - alpha);
+    let a = alpha + under;
+    if a > 0.0 {
+        for k in 0..3 {
+            p[k] = ((color[k] as f32 * alpha + p[k] as f32 * under) / a).round() as u8;
+        }
+    }
+    p[3] = (a * 255.0).round() as u8;
+}
+
+// pixels which may be inside a box, in image pixels
+fn raster_span(r:&Raster, x0:f64, y0:f64, x1:f64, y1:f64) -> (std::ops::Range<usize>,std::ops::Range<usize>) {
+    let clamp = |v:f64, max:usize| (v.max(0.0) as usize).min(max);
+    (clamp(x0.floor(), r.width)..clamp(x1.ceil(), r.width),
+     clamp(y0.floor(), r.height)..clamp(y1.ceil(), r.height))
+}
+
+/*
+A line from a to b, in page pixels, with round ends.  Lines thinner
+than an image pixel are drawn a pixel wide, but fainter.
+*/
+pub(crate) fn raster_line(r:&mut Raster, a:(f64,f64), b:(f64,f64), width:f64, color:Rgb) {
+    let s = r.scale;
+    let (ax, ay, dx, dy) = (a.0 * s, a.1 * s, (b.0 - a.0) * s, (b.1 - a.1) * s);
+    let w = width * s;
+    let half = (w / 2.0).max(0.5);
+    let faint = w.min(1.0);
+    let pad = half + 1.0;
+    let (is,js) = raster_span(r, ax.min(ax + dx) - pad, ay.min(ay + dy) - pad,
+        ax.max(ax + dx) + pad, ay.max(ay + dy) + pad);
+    let length2 = dx * dx + dy * dy;
+    for j in js {
+        for i in is.clone() {
+            // distance from the pixel center to the nearest point of the line
+            let (px, py) = (i as f64 + 0.5 - ax, j as f64 + 0.5 - ay);
+            let t = if length2 > 0.0 { ((px * dx + py * dy) / length2).clamp(0.0, 1.0) } else { 0.0 };
+            let d = (px - t * dx).hypot(py - t * dy);
+            let alpha = if r.opts.antialias {
+                (half + 0.5 - d).clamp(0.0, 1.0) * faint
+            }
+            else if d <= half { 1.0 } else { 0.0 };
+            if alpha > 0.0 {
+                raster_blend(r, i, j, color, alpha);
+            }
+        }
+    }
+}
+
+// inside by the nonzero winding rule, as svg, PostScript and pdf fill
+fn polygon_inside(vs:&[(f64,f64)], x:f64, y:f64) -> bool {
+    let mut winding = 0;
+    let mut j = vs.len() - 1;
+    for i in 0..vs.len() {
+        let ((xi,yi), (xj,yj)) = (vs[i], vs[j]);
+        if (yi > y) != (yj > y) && x < xi + (y - yi) * (xj - xi) / (yj - yi) {
+            winding += if yi > yj { 1 } else { -1 };
+        }
+        j = i;
+    }
+    winding != 0
+}
+
+// a filled polygon, its vertices in page pixels
+pub(crate) fn raster_polygon(r:&mut Raster, vs:&[(f64,f64)], color:Rgb) {
+    if vs.len() < 3 {
+        return;
+    }
+    let s = r.scale;
+    let vs:Vec<(f64,f64)> = vs.iter().map(|(x,y)| (x * s, y * s)).collect();
+    let x0 = vs.iter().map(|v| v.0).fold(f64::MAX, f64::min);
+    let y0 = vs.iter().map(|v| v.1).fold(f64::MAX, f64::min);
+    let x1 = vs.iter().map(|v| v.0).fold(f64::MIN, f64::max);
+    let y1 = vs.iter().map(|v| v.1).fold(f64::MIN, f64::max);
+    let n = if r.opts.antialias { 4 } else { 1 };
+    let (is,js) = raster_span(r, x0, y0, x1, y1);
+    for j in js {
+        for i in is.clone() {
+            let mut count = 0;
+            for sj in 0..n {
+                for si in 0..n {
+                    let x = i as f64 + (si as f64 + 0.5) / n as f64;
+                    let y = j as f64 + (sj as f64 + 0.5) / n as f64;
+                    count += polygon_inside(&vs, x, y) as usize;
+                }
+            }
+            if count > 0 {
+                raster_blend(r, i, j, color, count as f64 / (n * n) as f64);
+            }
+        }
+    }
+}
+
+// a rectangle in page pixels, covering each pixel by its share of it
+pub(crate) fn raster_rect(r:&mut Raster, x0:f64, y0:f64, x1:f64, y1:f64, color:Rgb) {
+    let s = r.scale;
+    let (x0, y0, x1, y1) = (x0 * s, y0 * s, x1 * s, y1 * s);
+    let (is,js) = raster_span(r, x0, y0, x1, y1);
+    for j in js {
+        for i in is.clone() {
+            let (fi, fj) = (i as f64, j as f64);
+            let alpha = if r.opts.antialias {
+                let cx = (x1.min(fi + 1.0) - x0.max(fi)).max(0.0);
+                let cy = (y1.min(fj + 1.0) - y0.max(fj)).max(0.0);
+                cx * cy
+            }
+            else {
+                let (cx, cy) = (fi + 0.5, fj + 0.5);
+                (x0 <= cx && cx < x1 && y0 <= cy && cy < y1) as u8 as f64
+            };
+            if alpha > 0.0 {
+                raster_blend(r, i, j, color, alpha);
+            }
+        }
+    }
+}
+
+/*
+PNG of lsys at order, scaled to fit the box pbb, in pixels, as
+lsys_draw_basic does for svg, drawn on r.  Also the box the drawing
+covers, with room for the width of its lines.
+*/
+pub(crate) fn png_draw_basic(r:&mut Raster, lsys:&LSys, order:i32, pbb:&BBox) -> Result<BBox,LSysError> {
+    let abb = lsys_dacts(lsys, lsys_derive(lsys,order)?, &mut |_| ())?;
+    let (pixel_per_step, mut x, mut y, drawn) = lsys_fit(&abb, pbb);
+
+    let rgb = |c:&str| color_rgb(c).unwrap_or([0, 0, 0]);
+    let mut color = rgb(lsys_color(lsys,0));
+    let mut width = r.opts.stroke_width;
+    let mut widest = width;
+    let mut polygons = vec!();
+    lsys_dacts(lsys, lsys_derive(lsys,order)?, &mut |dact| {
+        match dact {
+            DAct::RmoveTo(xs,ys) => {
+                x += pixel_per_step * xs;
+                y += pixel_per_step * ys;
+            }
+            DAct::RlineTo(xs,ys) => {
+                let (x1, y1) = (x + pixel_per_step * xs, y + pixel_per_step * ys);
+                raster_line(r, (x,y), (x1,y1), width, color);
+                (x, y) = (x1, y1);
+            }
+            DAct::Polygon(vs) => {
+                let vs:Vec<(f64,f64)> = vs.iter()
+                    .map(|(xs,ys)| (x + pixel_per_step * xs, y + pixel_per_step * ys))
+                    .collect();
+                polygons.push((vs, color));
+            }
+            DAct::Style(c,w) => {
+                color = rgb(lsys_color(lsys,c));
+                width = r.opts.stroke_width * w;
+                widest = widest.max(width);
+            }
+        }
+    })?;
+    for (vs,color) in polygons {
+        raster_polygon(r, &vs, color);
+    }
+
+    // and a pixel for the smoothed edge
+    let pad = widest / 2.0 + 1.0 / r.scale;
+    Ok((drawn.0 - pad, drawn.1 - pad, drawn.2 + pad, drawn.3 + pad))
+}
+
+/*----------------------------------------------------------------------
+Text, in a font of 5 by 7 dots, each row a byte with the leftmost dot
+in bit 4.  Glyphs stand on the baseline, with no descenders, and are 6
+dots apart.  A dot is a tenth of the size of the text.
+*/
+
+// ' ' to '~'
+static PNG_FONT:[[u8;7];95] = [
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00],   // space
+    [0x04, 0x04, 0x04, 0x04, 0x04, 0x00, 0x04],   // !
+    [0x0a, 0x0a, 0x0a, 0x00, 0x00, 0x00, 0x00],   // "
+    [0x0a, 0x0a, 0x1f, 0x0a, 0x1f, 0x0a, 0x0a],   // #
+    [0x04, 0x0f, 0x14, 0x0e, 0x05, 0x1e, 0x04],   // $
+    [0x18, 0x19, 0x02, 0x04, 0x08, 0x13, 0x03],   // %
+    [0x0c, 0x12, 0x14, 0x08, 0x15, 0x12, 0x0d],   // &
+    [0x04, 0x04, 0x04, 0x00, 0x00, 0x00, 0x00],   // '
+    [0x02, 0x04, 0x08, 0x08, 0x08, 0x04, 0x02],   // (
+    [0x08, 0x04, 0x02, 0x02, 0x02, 0x04, 0x08],   // )
+    [0x00, 0x04, 0x15, 0x0e, 0x15, 0x04, 0x00],   // *
+    [0x00, 0x04, 0x04, 0x1f, 0x04, 0x04, 0x00],   // +
+    [0x00, 0x00, 0x00, 0x00, 0x0c, 0x04, 0x08],   // ,
+    [0x00, 0x00, 0x00, 0x1f, 0x00, 0x00, 0x00],   // -
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x0c, 0x0c],   // .
+    [0x00, 0x01, 0x02, 0x04, 0x08, 0x10, 0x00],   // /
+    [0x0e, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0e],   // 0
+    [0x04, 0x0c, 0x04, 0x04, 0x04, 0x04, 0x0e],   // 1
+    [0x0e, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1f],   // 2
+    [0x1f, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0e],   // 3
+    [0x02, 0x06, 0x0a, 0x12, 0x1f, 0x02, 0x02],   // 4
+    [0x1f, 0x10, 0x1e, 0x01, 0x01, 0x11, 0x0e],   // 5
+    [0x06, 0x08, 0x10, 0x1e, 0x11, 0x11, 0x0e],   // 6
+    [0x1f, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08],   // 7
+    [0x0e, 0x11, 0x11, 0x0e, 0x11, 0x11, 0x0e],   // 8
+    [0x0e, 0x11, 0x11, 0x0f, 0x01, 0x02, 0x0c],   // 9
+    [0x00, 0x0c, 0x0c, 0x00, 0x0c, 0x0c, 0x00],   // :
+    [0x00, 0x0c, 0x0c, 0x00, 0x0c, 0x04, 0x08],   // ;
+    [0x02, 0x04, 0x08, 0x10, 0x08, 0x04, 0x02],   // <
+    [0x00, 0x00, 0x1f, 0x00, 0x1f, 0x00, 0x00],   // =
+    [0x08, 0x04, 0x02, 0x01, 0x02, 0x04, 0x08],   // >
+    [0x0e, 0x11, 0x01, 0x02, 0x04, 0x00, 0x04],   // ?
+    [0x0e, 0x11, 0x01, 0x0d, 0x15, 0x15, 0x0e],   // @
+    [0x0e, 0x11, 0x11, 0x1f, 0x11, 0x11, 0x11],   // A
+    [0x1e, 0x11, 0x11, 0x1e, 0x11, 0x11, 0x1e],   // B
+    [0x0e, 0x11, 0x10, 0x10, 0x10, 0x11, 0x0e],   // C
+    [0x1c, 0x12, 0x11, 0x11, 0x11, 0x12, 0x1c],   // D
+    [0x1f, 0x10, 0x10, 0x1e, 0x10, 0x10, 0x1f],   // E
+    [0x1f, 0x10, 0x10, 0x1e, 0x10, 0x10, 0x10],   // F
+    [0x0e, 0x11, 0x10, 0x17, 0x11, 0x11, 0x0f],   // G
+    [0x11, 0x11, 0x11, 0x1f, 0x11, 0x11, 0x11],   // H
+    [0x0e, 0x04, 0x04, 0x04, 0x04, 0x04, 0x0e],   // I
+    [0x07, 0x02, 0x02, 0x02, 0x02, 0x12, 0x0c],   // J
+    [0x11, 0x12, 0x14, 0x18, 0x14, 0x12, 0x11],   // K
+    [0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x1f],   // L
+    [0x11, 0x1b, 0x15, 0x15, 0x11, 0x11, 0x11],   // M
+    [0x11, 0x11, 0x19, 0x15, 0x13, 0x11, 0x11],   // N
+    [0x0e, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0e],   // O
+    [0x1e, 0x11, 0x11, 0x1e, 0x10, 0x10, 0x10],   // P
+    [0x0e, 0x11, 0x11, 0x11, 0x15, 0x12, 0x0d],   // Q
+    [0x1e, 0x11, 0x11, 0x1e, 0x14, 0x12, 0x11],   // R
+    [0x0f, 0x10, 0x10, 0x0e, 0x01, 0x01, 0x1e],   // S
+    [0x1f, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04],   // T
+    [0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0e],   // U
+    [0x11, 0x11, 0x11, 0x11, 0x11, 0x0a, 0x04],   // V
+    [0x11, 0x11, 0x11, 0x15, 0x15, 0x15, 0x0a],   // W
+    [0x11, 0x11, 0x0a, 0x04, 0x0a, 0x11, 0x11],   // X
+    [0x11, 0x11, 0x0a, 0x04, 0x04, 0x04, 0x04],   // Y
+    [0x1f, 0x01, 0x02, 0x04, 0x08, 0x10, 0x1f],   // Z
+    [0x0e, 0x08, 0x08, 0x08, 0x08, 0x08, 0x0e],   // [
+    [0x00, 0x10, 0x08, 0x04, 0x02, 0x01, 0x00],   // \
+    [0x0e, 0x02, 0x02, 0x02, 0x02, 0x02, 0x0e],   // ]
+    [0x04, 0x0a, 0x11, 0x00, 0x00, 0x00, 0x00],   // ^
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x1f],   // _
+    [0x08, 0x04, 0x02, 0x00, 0x00, 0x00, 0x00],   // `
+    [0x00, 0x00, 0x0e, 0x01, 0x0f, 0x11, 0x0f],   // a
+    [0x10, 0x10, 0x16, 0x19, 0x11, 0x11, 0x1e],   // b
+    [0x00, 0x00, 0x0e, 0x10, 0x10, 0x11, 0x0e],   // c
+    [0x01, 0x01, 0x0d, 0x13, 0x11, 0x11, 0x0f],   // d
+    [0x00, 0x00, 0x0e, 0x11, 0x1f, 0x10, 0x0e],   // e
+    [0x06, 0x09, 0x08, 0x1c, 0x08, 0x08, 0x08],   // f
+    [0x00, 0x0f, 0x11, 0x11, 0x0f, 0x01, 0x0e],   // g
+    [0x10, 0x10, 0x16, 0x19, 0x11, 0x11, 0x11],   // h
+    [0x04, 0x00, 0x0c, 0x04, 0x04, 0x04, 0x0e],   // i
+    [0x02, 0x00, 0x06, 0x02, 0x02, 0x12, 0x0c],   // j
+    [0x10, 0x10, 0x12, 0x14, 0x18, 0x14, 0x12],   // k
+    [0x0c, 0x04, 0x04, 0x04, 0x04, 0x04, 0x0e],   // l
+    [0x00, 0x00, 0x1a, 0x15, 0x15, 0x11, 0x11],   // m
+    [0x00, 0x00, 0x16, 0x19, 0x11, 0x11, 0x11],   // n
+    [0x00, 0x00, 0x0e, 0x11, 0x11, 0x11, 0x0e],   // o
+    [0x00, 0x00, 0x1e, 0x11, 0x1e, 0x10, 0x10],   // p
+    [0x00, 0x00, 0x0d, 0x13, 0x0f, 0x01, 0x01],   // q
+    [0x00, 0x00, 0x16, 0x19, 0x10, 0x10, 0x10],   // r
+    [0x00, 0x00, 0x0e, 0x10, 0x0e, 0x01, 0x1e],   // s
+    [0x08, 0x08, 0x1c, 0x08, 0x08, 0x09, 0x06],   // t
+    [0x00, 0x00, 0x11, 0x11, 0x11, 0x13, 0x0d],   // u
+    [0x00, 0x00, 0x11, 0x11, 0x11, 0x0a, 0x04],   // v
+    [0x00, 0x00, 0x11, 0x11, 0x15, 0x15, 0x0a],   // w
+    [0x00, 0x00, 0x11, 0x0a, 0x04, 0x0a, 0x11],   // x
+    [0x00, 0x00, 0x11, 0x11, 0x0f, 0x01, 0x0e],   // y
+    [0x00, 0x00, 0x1f, 0x02, 0x04, 0x08, 0x1f],   // z
+    [0x02, 0x04, 0x04, 0x08, 0x04, 0x04, 0x02],   // {
+    [0x04, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04],   // |
+    [0x08, 0x04, 0x04, 0x02, 0x04, 0x04, 0x08],   // }
+    [0x00, 0x00, 0x08, 0x15, 0x02, 0x00, 0x00],   // ~
+];
+
+// width of text in the font, in units of its size
+pub(crate) fn png_text_width(text:&str) -> f64 {
+    let n = text.chars().count() as f64;
+    if n > 0.0 { n * 0.6 - 0.1 } else { 0.0 }
+}
+
+/*
+Lines of text, as svg_draw_text_lines draws them, the first line a
+line height below y.  Bold dots are half as wide again.
+*/
+pub(crate) fn png_text_lines(r:&mut Raster, x:f64, y:f64, td:&TextDesc, lines:&[String]) {
+    let dot = td.size / 10.0;
+    let wide = if td.weight.starts_with("bold") { 1.5 * dot } else { dot };
+    for (i,line) in lines.iter().enumerate() {
+        let base = y + 1.2 * td.size * (i + 1) as f64;
+        let width = png_text_width(line) * td.size;
+        let mut left = match td.anchor {
+            "middle" => x - width / 2.0,
+            "end"    => x - width,
+            _        => x,
+        };
+        for c in line.chars() {
+            let c = if (' '..='~').contains(&c) { c } else { '?' };
+            for (row,bits) in PNG_FONT[c as usize - ' ' as usize].iter().enumerate() {
+                let top = base - (7 - row) as f64 * dot;
+                for col in (0..5).filter(|col| bits & (0x10 >> col) != 0) {
+                    let x0 = left + col as f64 * dot;
+                    raster_rect(r, x0, top, x0 + wide, top + dot, [0, 0, 0]);
+                }
+            }
+            left += 6.0 * dot;
+        }
+    }
+}
+
+/*----------------------------------------------------------------------
+Encoding
+*/
+
+// crc of png chunks, reflected, polynomial 0xedb88320
+pub(crate) fn png_crc32(bytes:&[u8]) -> u32 {
+    let mut table = [0u32; 256];
+    for (n,entry) in table.iter_mut().enumerate() {
+        let mut c = n as u32;
+        for _ in 0..8 {
+            c = if c & 1 != 0 { 0xedb88320 ^ (c >> 1) } else { c >> 1 };
+        }
+        *entry = c;
+    }
+    !bytes.iter().fold(!0u32, |c,&b| table[((c ^ b as u32) & 0xff) as usize] ^ (c >> 8))
+}
+
+fn png_chunk(out:&mut Vec<u8>, kind:&[u8;4], data:&[u8]) {
+    out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = out.len();
+    out.extend_from_slice(kind);
+    out.extend_from_slice(data);
+    let crc = png_crc32(&out[start..]);
+    out.extend_from_slice(&crc.to_be_bytes());
+}
+
+// the part of r inside crop, in page pixels, as a png file
+pub(crate) fn png_encode(r:&Raster, crop:&BBox) -> Vec<u8> {
+    let s = r.scale;
+    let (is,js) = raster_span(r, crop.0 * s, crop.1 * s, crop.2 * s, crop.3 * s);
+    let (is,js) = if is.is_empty() || js.is_empty() { (0..r.width, 0..r.height) } else { (is,js) };
+
+    // each row starts with its filter, none
+    let mut data = Vec::with_capacity(js.len() * (1 + 4 * is.len()));
+    for j in js.clone() {
+        data.push(0);
+        for p in &r.pixels[j * r.width + is.start..j * r.width + is.end] {
+            data.extend_from_slice(p);
+        }
+    }
+
+    let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+    let mut ihdr = vec!();
+    ihdr.extend_from_slice(&(is.len() as u32).to_be_bytes());
+    ihdr.extend_from_slice(&(js.len() as u32).to_be_bytes());
+    ihdr.extend_from_slice(&[8, 6, 0, 0, 0]);  // 8 bit rgba, no interlace
+    png_chunk(&mut png, b"IHDR", &ihdr);
+    // resolution, in pixels per meter
+    let ppm = (r.opts.dpi / 0.0254).round() as u32;
+    let mut phys = vec!();
+    phys.extend_from_slice(&ppm.to_be_bytes());
+    phys.extend_from_slice(&ppm.to_be_bytes());
+    phys.push(1);
+    png_chunk(&mut png, b"pHYs", &phys);
+    png_chunk(&mut png, b"IDAT", &zlib::zlib_compress(&data));
+    png_chunk(&mut png, b"IEND", &[]);
+    png
+}
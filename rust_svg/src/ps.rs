@@ -0,0 +1,293 @@
+use super::*;
+
+/*----------------------------------------------------------------------
+PostScript output
+
+This is the output of the earlier python version, py_ps/lsys.py, done
+with the same layout as the svg.  Documents follow the Adobe Document
+Structuring Conventions (DSC), version 3.0:
+
+    https://en.wikipedia.org/wiki/Document_Structuring_Conventions
+    https://www-cdf.fnal.gov/offline/PostScript/5001.PDF
+
+A PostScript document has a page for each LSys, and since pages may
+be skipped the count is given at the end, in the trailer.  Encapsulated
+PostScript (EPS) has a single page, and its bounding box is that of
+what is drawn, so a figure on its own is cropped tight.  No creation
+date is written, so the same input always gives the same output.
+
+Each page is turned upside down and scaled from points to pixels, so
+that it is drawn in the same coordinates as the svg, from the same
+layout boxes.  Fonts are flipped back with makefont, so text is still
+upright.  References become links with pdfmark, which turns into link
+annotations when the PostScript is converted to PDF, and does nothing
+when printed.  The pdfmark rectangles are given in default user space,
+which is why they are drawn after initmatrix.
+*/
+
+pub(crate) static POINT_PER_PIXEL:f64 = 72.0 / PIXEL_PER_INCH;   // points/pixel
+static PS_SEGMENTS_MAX:usize = 1000;                    // in one stroke
+
+// procedures, kept in a dictionary of their own, as eps must
+static PS_PROLOG:&str = indoc! {r#"
+    %%BeginProlog
+    /lsys_dict 16 dict def
+    lsys_dict begin
+    /m {moveto} bind def
+    /l {lineto} bind def
+    /S {stroke} bind def
+    /h {closepath} bind def
+    /f {fill} bind def
+    /c {setrgbcolor} bind def
+    /w {setlinewidth} bind def
+    end
+    /pdfmark where {pop} {userdict /pdfmark /cleartomark load put} ifelse
+    %%EndProlog
+"#};
+
+// a PostScript string, with only ascii characters
+pub(crate) fn ps_string(s:&str) -> String {
+    let mut out = String::from("(");
+    for c in s.chars() {
+        match c {
+            '(' | ')' | '\\' => { out.push('\\'); out.push(c); }
+            ' '..='~' => out.push(c),
+            _ => out.push('?'),
+        }
+    }
+    out.push(')');
+    out
+}
+
+// a color of the color map as "r g b op", black if it is not known
+fn ps_color(color:&str, op:&str) -> String {
+    let [r,g,b] = color::color_rgb(color).unwrap_or([0,0,0]);
+    format!("{:.3} {:.3} {:.3} {op}", r as f64/255.0, g as f64/255.0, b as f64/255.0)
+}
+
+// page coordinates, in pixels, as default user space in points
+pub(crate) fn ps_point(x:f64, y:f64) -> (f64,f64) {
+    (x * POINT_PER_PIXEL, (PAGE_HEIGHT - y) * POINT_PER_PIXEL)
+}
+
+/*
+Everything up to the first page.  An eps document gives the bounding
+box of what is drawn, in pixels, and the fonts only when it has text.
+*/
+pub(crate) fn ps_doc_head(title:&str, eps:Option<BBox>, fonts:bool) -> String {
+    let (x0,y0) = ps_point(0.0, PAGE_HEIGHT);
+    let (x1,y1) = ps_point(PAGE_WIDTH, 0.0);
+    let (version, bbox, pages) = match eps {
+        None => ("", (x0,y0,x1,y1), "(atend)"),
+        Some(bb) => {
+            let (x0,y0) = ps_point(bb.0, bb.3);
+            let (x1,y1) = ps_point(bb.2, bb.1);
+            (" EPSF-3.0", (x0,y0,x1,y1), "1")
+        }
+    };
+    let title:String = title.chars().map(|c| if c.is_control() { ' ' } else { c }).collect();
+    let mut head = format!( indoc! {r#"
+        %!PS-Adobe-3.0{version}
+        %%Title: {title}
+        %%Creator: rust_svg
+        %%BoundingBox: {bx0} {by0} {bx1} {by1}
+        %%HiResBoundingBox: {x0:.2} {y0:.2} {x1:.2} {y1:.2}
+        %%LanguageLevel: 2
+        %%Pages: {pages}
+        "#},
+        bx0 = bbox.0.floor(), by0 = bbox.1.floor(),
+        bx1 = bbox.2.ceil(),  by1 = bbox.3.ceil(),
+        x0 = bbox.0, y0 = bbox.1, x1 = bbox.2, y1 = bbox.3,
+        version = version, title = title, pages = pages,
+    );
+    if eps.is_none() {
+        head.push_str("%%Orientation: Portrait\n");
+    }
+    if fonts {
+        head.push_str("%%DocumentNeededResources: font Times-Bold Helvetica Courier\n");
+    }
+    head.push_str("%%EndComments\n");
+    head.push_str(PS_PROLOG);
+    head
+}
+
+pub(crate) fn ps_page_head(page_no:usize, comment:&str) -> String {
+    format!( indoc! {r#"
+
+        %%Page: {page_no} {page_no}
+        %%BeginPageSetup
+        /pagesave save def
+        lsys_dict begin
+        0 {height} translate
+        {scale} -{scale} scale
+        4 setmiterlimit
+        %%EndPageSetup
+        % {comment}
+        "#},
+        page_no = page_no,
+        height = PAGE_HEIGHT * POINT_PER_PIXEL,
+        scale = POINT_PER_PIXEL,
+        comment = comment.replace(['\n', '\r'], " "),
+    )
+}
+
+pub(crate) fn ps_page_foot(page_no:usize) -> String {
+    format!( indoc! {r#"
+
+        end
+        pagesave restore
+        showpage
+        % end page {page_no}
+        "#},
+        page_no = page_no,
+    )
+}
+
+// the trailer, with the number of pages unless eps
+pub(crate) fn ps_doc_foot(pages:Option<usize>) -> String {
+    let mut foot = String::from("\n%%Trailer\n");
+    if let Some(n) = pages {
+        foot.push_str(&format!("%%Pages: {n}\n"));
+    }
+    foot.push_str("%%EOF\n");
+    foot
+}
+
+/*
+PostScript of lsys at order, scaled to fit the box pbb, in pixels, as
+lsys_draw_basic does for svg.  Also the box the drawing covers, with
+room for the width of its lines.
+
+A pdf content stream uses the same path operators, so with pdf this
+is drawn for pdf.rs, with its own operators for colors and for saving
+the graphics state.
+*/
+pub(crate) fn ps_draw_basic(lsys:&LSys, order:i32, pbb:&BBox, pdf:bool) -> Result<(String,BBox),LSysError> {
+    let abb = lsys_dacts(lsys, lsys_derive(lsys,order)?, &mut |_| ())?;
+    let (pixel_per_step, mut x, mut y, drawn) = lsys_fit(&abb, pbb);
+
+    let (save, restore, stroke, fill) =
+        if pdf { ("q", "Q", "RG", "rg") } else { ("gsave", "grestore", "c", "c") };
+    let mut ps = format!("{save}\n");
+    let mut polygons = String::new();
+    let mut color = lsys_color(lsys,0);
+    let mut widest = 1.0f64;
+    ps.push_str(&format!("{} {:.2} w\n", ps_color(color, stroke), STROKE_WIDTH));
+    ps.push_str(&format!("{x:.2} {y:.2} m\n"));
+
+    // as for svg, five actions to a line, and a new stroke for each
+    // style, and after PS_SEGMENTS_MAX segments
+    let mut col = 0;
+    let mut segments = 0;
+    lsys_dacts(lsys, lsys_derive(lsys,order)?, &mut |dact| {
+        match dact {
+            DAct::RmoveTo(xs,ys) => {
+                x += pixel_per_step * xs;
+                y += pixel_per_step * ys;
+                ps.push_str(&format!("{x:.2} {y:.2} m "));
+            }
+            DAct::RlineTo(xs,ys) => {
+                x += pixel_per_step * xs;
+                y += pixel_per_step * ys;
+                ps.push_str(&format!("{x:.2} {y:.2} l "));
+                segments += 1;
+            }
+            DAct::Polygon(vs) => {
+                // pdf allows no color within a path, so it comes first
+                polygons.push_str(&format!("{} ", ps_color(color, fill)));
+                let mut op = "m";
+                for (xs,ys) in vs {
+                    polygons.push_str(&format!("{:.2} {:.2} {op} ",
+                        x + pixel_per_step * xs, y + pixel_per_step * ys));
+                    op = "l";
+                }
+                polygons.push_str("h f\n");
+                return;
+            }
+            DAct::Style(c,w) => {
+                color = lsys_color(lsys,c);
+                widest = widest.max(w);
+                ps.push_str(&format!("S\n{} {:.2} w\n{x:.2} {y:.2} m ",
+                    ps_color(color, stroke), STROKE_WIDTH * w));
+                col = 0;
+                return;
+            }
+        }
+        col += 1;
+        if segments >= PS_SEGMENTS_MAX {
+            ps.push_str(&format!("S\n{x:.2} {y:.2} m"));
+            segments = 0;
+            col = 5;
+        }
+        if col >= 5 {
+            ps.push('\n');
+            col = 0;
+        }
+    })?;
+    if col > 0 {
+        ps.push('\n');
+    }
+    ps.push_str("S\n");
+    ps.push_str(&polygons);
+    ps.push_str(&format!("{restore}\n"));
+
+    let pad = STROKE_WIDTH * widest / 2.0;
+    let drawn = (drawn.0 - pad, drawn.1 - pad, drawn.2 + pad, drawn.3 + pad);
+    Ok((ps,drawn))
+}
+
+// the standard font nearest to the family and weight of td
+fn ps_font(td:&TextDesc) -> &'static str {
+    let bold = td.weight.starts_with("bold");
+    match (td.family, bold) {
+        ("serif", true)      => "Times-Bold",
+        ("serif", false)     => "Times-Roman",
+        ("monospace", true)  => "Courier-Bold",
+        ("monospace", false) => "Courier",
+        (_, true)            => "Helvetica-Bold",
+        (_, false)           => "Helvetica",
+    }
+}
+
+/*
+Lines of text, as svg_draw_text_lines draws them, the first line a
+line height below y.  With links, lines of link text are also links
+to themselves.
+*/
+pub(crate) fn ps_text_lines(
+    ps:&mut String,
+    x:f64,
+    y:f64,
+    td:&TextDesc,
+    lines:&[String],
+    links:bool,
+) {
+    let size = td.size;
+    ps.push_str(&format!("/{} findfont [{size} 0 0 -{size} 0 0] makefont setfont\n", ps_font(td)));
+    for (i,line) in lines.iter().enumerate() {
+        let text = ps_string(line);
+        let base = y + 1.2 * size * (i + 1) as f64;
+        let shift = match td.anchor {
+            "middle" => format!(" {text} stringwidth pop 2 div sub"),
+            "end"    => format!(" {text} stringwidth pop sub"),
+            _        => String::new(),
+        };
+        ps.push_str(&format!("{x:.2}{shift} {base:.2} m {text} show\n"));
+        if links && td.link {
+            let (x0,y0) = ps_point(x, base + 0.25 * size);
+            let (_,y1) = ps_point(x, base - size);
+            ps.push_str(&format!( indoc! {r#"
+                gsave initmatrix
+                [ /Rect [{x0:.2} {y0:.2} {x0:.2} {text} stringwidth pop {scale} mul add {y1:.2}]
+                  /Action << /Subtype /URI /URI {text} >>
+                  /Border [0 0 1] /Color [0 0 1]
+                  /Subtype /Link
+                /ANN pdfmark
+                grestore
+                "#},
+                x0 = x0, y0 = y0, y1 = y1, text = text,
+                scale = POINT_PER_PIXEL,
+            ));
+        }
+    }
+}
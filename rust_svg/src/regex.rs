@@ -0,0 +1,332 @@
+/*----------------------------------------------------------------------
+Regular expressions, for choosing LSys by title
+
+Only what is useful on a title is supported.  A regex matches if it
+matches anywhere in the text, unless anchored by "^" or "$".
+
+Grammar, from lowest to highest precedence:
+
+    alt    := seq { "|" seq }
+    seq    := { repeat }
+    repeat := atom { "*" | "+" | "?" | "{" n [ "," [ m ] ] "}" }
+    atom   := char | "." | "^" | "$" | "\" char | class | "(" alt ")"
+    class  := "[" [ "^" ] { char | char "-" char | "\" char } "]"
+
+"\d", "\w" and "\s" stand for digits, word characters and white
+space, and any other escaped character stands for itself.  Counts of
+repeats are at most REPEAT_MAX, and since a repeat is compiled as that
+many copies, the whole program is at most PROGRAM_MAX instructions, so
+that nested repeats such as "((a{1000}){1000}){1000}" are refused.
+
+The regex is compiled into instructions, which are then run for every
+way through them at once, a step for each character of the text, as
+in Thompson's construction or a Pike VM.  A way that reaches an
+instruction already reached at the same step is dropped, so that
+matching takes time in proportion to the lengths of regex and text,
+never exponential as backtracking can, even for "^(a*)*b".
+
+    https://swtch.com/~rsc/regexp/regexp1.html
+*/
+
+static REPEAT_MAX:usize  = 1000;
+static PROGRAM_MAX:usize = 100_000;       // instructions
+
+/// A parsed regex, see regex_parse
+#[derive(Debug, Clone, PartialEq)]
+pub enum Regex {
+    /// A literal character
+    Char(char),
+    /// "."
+    Any,
+    /// Negated, ranges
+    Class(bool, Vec<(char,char)>),
+    /// "^"
+    Start,
+    /// "$"
+    End,
+    /// One after another
+    Seq(Vec<Regex>),
+    /// Any one of them
+    Alt(Vec<Regex>),
+    /// Min and max times
+    Repeat(Box<Regex>, usize, usize),
+}
+
+struct Parser<'a> {
+    src:   &'a str,
+    chars: Vec<char>,
+    pos:   usize,
+}
+
+fn parser_peek(p:&Parser) -> Option<char> {
+    p.chars.get(p.pos).copied()
+}
+
+fn parser_next(p:&mut Parser) -> Result<char,String> {
+    let c = parser_peek(p).ok_or_else(|| format!("Unexpected end of '{}'", p.src))?;
+    p.pos += 1;
+    Ok(c)
+}
+
+fn parser_number(p:&mut Parser) -> Option<usize> {
+    let j = p.pos;
+    while parser_peek(p).is_some_and(|c| c.is_ascii_digit()) {
+        p.pos += 1;
+    }
+    p.chars[j..p.pos].iter().collect::<String>().parse().ok()
+}
+
+// ranges of the classes "\d", "\w" and "\s", or the escaped char itself
+fn escape_ranges(c:char) -> (bool, Vec<(char,char)>) {
+    match c {
+        'd' => (true, vec![('0','9')]),
+        'w' => (true, vec![('a','z'), ('A','Z'), ('0','9'), ('_','_')]),
+        's' => (true, vec![(' ',' '), ('\t','\r')]),
+        _   => (false, vec![(c,c)]),
+    }
+}
+
+fn parse_alt(p:&mut Parser) -> Result<Regex,String> {
+    let mut alts = vec![parse_seq(p)?];
+    while parser_peek(p) == Some('|') {
+        p.pos += 1;
+        alts.push(parse_seq(p)?);
+    }
+    Ok(if alts.len() == 1 { alts.remove(0) } else { Regex::Alt(alts) })
+}
+
+fn parse_seq(p:&mut Parser) -> Result<Regex,String> {
+    let mut seq = vec!();
+    while let Some(c) = parser_peek(p) {
+        if c == '|' || c == ')' {
+            break;
+        }
+        seq.push(parse_repeat(p)?);
+    }
+    Ok(Regex::Seq(seq))
+}
+
+fn parse_repeat(p:&mut Parser) -> Result<Regex,String> {
+    let mut re = parse_atom(p)?;
+    loop {
+        let (min,max) = match parser_peek(p) {
+            Some('*') => (0, usize::MAX),
+            Some('+') => (1, usize::MAX),
+            Some('?') => (0, 1),
+            Some('{') => {
+                p.pos += 1;
+                let Some(min) = parser_number(p) else {
+                    return Err(format!("Expected a count after '{{' in '{}'", p.src));
+                };
+                let mut max = min;
+                if parser_peek(p) == Some(',') {
+                    p.pos += 1;
+                    max = parser_number(p).unwrap_or(usize::MAX);
+                }
+                if parser_peek(p) != Some('}') || max < min {
+                    return Err(format!("Bad repeat count in '{}'", p.src));
+                }
+                if min > REPEAT_MAX || (max > REPEAT_MAX && max != usize::MAX) {
+                    return Err(format!("Repeat count above {REPEAT_MAX} in '{}'", p.src));
+                }
+                (min,max)
+            }
+            _ => return Ok(re),
+        };
+        p.pos += 1;
+        re = Regex::Repeat(Box::new(re), min, max);
+    }
+}
+
+fn parse_atom(p:&mut Parser) -> Result<Regex,String> {
+    match parser_next(p)? {
+        '.'  => Ok(Regex::Any),
+        '^'  => Ok(Regex::Start),
+        '$'  => Ok(Regex::End),
+        '\\' => {
+            let (class,ranges) = escape_ranges(parser_next(p)?);
+            Ok(if class { Regex::Class(false, ranges) } else { Regex::Char(ranges[0].0) })
+        }
+        '(' => {
+            let re = parse_alt(p)?;
+            if parser_peek(p) != Some(')') {
+                return Err(format!("Unbalanced '(' in '{}'", p.src));
+            }
+            p.pos += 1;
+            Ok(re)
+        }
+        '[' => {
+            let negated = parser_peek(p) == Some('^');
+            if negated {
+                p.pos += 1;
+            }
+            let mut ranges = vec!();
+            loop {
+                let c = parser_next(p)
+                    .map_err(|_| format!("Unbalanced '[' in '{}'", p.src))?;
+                match c {
+                    ']' if !ranges.is_empty() => break,
+                    '\\' => ranges.extend(escape_ranges(parser_next(p)?).1),
+                    _ if parser_peek(p) == Some('-')
+                        && p.chars.get(p.pos+1).is_some_and(|&d| d != ']') =>
+                    {
+                        p.pos += 1;
+                        let d = parser_next(p)?;
+                        ranges.push((c,d));
+                    }
+                    _ => ranges.push((c,c)),
+                }
+            }
+            Ok(Regex::Class(negated, ranges))
+        }
+        c @ ('*' | '+' | '?' | '{') => Err(format!("Nothing to repeat before '{c}' in '{}'", p.src)),
+        ')' => Err(format!("Unbalanced ')' in '{}'", p.src)),
+        c => Ok(Regex::Char(c)),
+    }
+}
+
+/// Parse src, or say why not
+pub fn regex_parse(src:&str) -> Result<Regex,String> {
+    let mut p = Parser { src, chars: src.chars().collect(), pos: 0 };
+    let re = parse_alt(&mut p)?;
+    if p.pos < p.chars.len() {
+        return Err(format!("Unbalanced ')' in '{src}'"));
+    }
+    regex_program(&re).map_err(|why| format!("{why} in '{src}'"))?;
+    Ok(re)
+}
+
+// an instruction, the next one following unless it says otherwise
+#[derive(Debug, Clone, PartialEq)]
+enum Inst {
+    Char(char),
+    Any,
+    Class(bool, Vec<(char,char)>),
+    Start,
+    End,
+    Split(usize, usize),        // go both ways
+    Jump(usize),
+    Match,
+}
+
+// stops as soon as the program is too long, so before copies of a
+// repeat take much memory
+fn regex_compile(re:&Regex, prog:&mut Vec<Inst>) -> Result<(),String> {
+    if prog.len() > PROGRAM_MAX {
+        return Err(format!("Regex longer than {PROGRAM_MAX} instructions"));
+    }
+    match re {
+        Regex::Char(c) => prog.push(Inst::Char(*c)),
+        Regex::Any     => prog.push(Inst::Any),
+        Regex::Class(negated,ranges) => prog.push(Inst::Class(*negated, ranges.clone())),
+        Regex::Start   => prog.push(Inst::Start),
+        Regex::End     => prog.push(Inst::End),
+        Regex::Seq(seq) => {
+            for re in seq {
+                regex_compile(re, prog)?;
+            }
+        }
+        Regex::Alt(alts) => {
+            let mut jumps = vec!();
+            for (n,re) in alts.iter().enumerate() {
+                if n + 1 == alts.len() {
+                    regex_compile(re, prog)?;
+                    break;
+                }
+                let split = prog.len();
+                prog.push(Inst::Split(split + 1, 0));
+                regex_compile(re, prog)?;
+                jumps.push(prog.len());
+                prog.push(Inst::Jump(0));
+                prog[split] = Inst::Split(split + 1, prog.len());
+            }
+            for j in jumps {
+                prog[j] = Inst::Jump(prog.len());
+            }
+        }
+        Regex::Repeat(re,min,max) => {
+            for _ in 0..*min {
+                regex_compile(re, prog)?;
+            }
+            if *max == usize::MAX {
+                let split = prog.len();
+                prog.push(Inst::Split(split + 1, 0));
+                regex_compile(re, prog)?;
+                prog.push(Inst::Jump(split));
+                prog[split] = Inst::Split(split + 1, prog.len());
+                return Ok(());
+            }
+            // each optional copy may skip to the end of them all
+            let mut splits = vec!();
+            for _ in *min..*max {
+                splits.push(prog.len());
+                prog.push(Inst::Split(prog.len() + 1, 0));
+                regex_compile(re, prog)?;
+            }
+            for s in splits {
+                prog[s] = Inst::Split(s + 1, prog.len());
+            }
+        }
+    }
+    Ok(())
+}
+
+fn regex_program(re:&Regex) -> Result<Vec<Inst>,String> {
+    let mut prog = vec!();
+    regex_compile(re, &mut prog)?;
+    prog.push(Inst::Match);
+    Ok(prog)
+}
+
+// add pc to the ways at position i, following those which read nothing
+fn regex_add(prog:&[Inst], ways:&mut Vec<usize>, seen:&mut [bool], pc:usize, i:usize, n:usize) {
+    if seen[pc] {
+        return;
+    }
+    seen[pc] = true;
+    match prog[pc] {
+        Inst::Start => if i == 0 { regex_add(prog, ways, seen, pc + 1, i, n) },
+        Inst::End   => if i == n { regex_add(prog, ways, seen, pc + 1, i, n) },
+        Inst::Jump(to) => regex_add(prog, ways, seen, to, i, n),
+        Inst::Split(a,b) => {
+            regex_add(prog, ways, seen, a, i, n);
+            regex_add(prog, ways, seen, b, i, n);
+        }
+        _ => ways.push(pc),
+    }
+}
+
+/// True if re matches anywhere in text, false if it is too long to run
+pub fn regex_find(re:&Regex, text:&str) -> bool {
+    let Ok(prog) = regex_program(re) else {
+        return false;
+    };
+    let text:Vec<char> = text.chars().collect();
+    let n = text.len();
+    let mut ways = vec!();
+    let mut seen = vec![false; prog.len()];
+    for i in 0..=n {
+        // a match may also start here
+        regex_add(&prog, &mut ways, &mut seen, 0, i, n);
+        if ways.iter().any(|&pc| prog[pc] == Inst::Match) {
+            return true;
+        }
+        let Some(&c) = text.get(i) else { break };
+        let mut next = vec!();
+        let mut next_seen = vec![false; prog.len()];
+        for &pc in &ways {
+            let step = match &prog[pc] {
+                Inst::Char(d) => c == *d,
+                Inst::Any     => true,
+                Inst::Class(negated,ranges) => ranges.iter().any(|&(a,b)| a <= c && c <= b) != *negated,
+                _ => false,
+            };
+            if step {
+                regex_add(&prog, &mut next, &mut next_seen, pc + 1, i + 1, n);
+            }
+        }
+        ways = next;
+        seen = next_seen;
+    }
+    false
+}
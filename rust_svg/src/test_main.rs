@@ -53,20 +53,15 @@ fn test_serde() {
     "#};
 
     // covert json to vector of LSys structs
-    let chunks = json_to_chunks(json1);
-    //println!("{:#?}",chunks);
-    let lsysv1 = lsys_from_json_chunks(&chunks);
+    let (lsysv1,notes) = lsys_json_parse(json1);
+    assert_eq!(notes, vec!());
 
-    // convert back to json
-    let mut json2 = String::new();
-    for lsys in &lsysv1 {
-        json2.push_str(&serde_json::to_string_pretty(&lsys).unwrap());
-        json2.push_str(&"\n\n");
-    }
+    // convert back to json, as an array
+    let json2 = serde_json::to_string_pretty(&lsysv1).unwrap();
 
     // convert to structs again
-    let chunks = json_to_chunks(&json2);
-    let lsysv2 = lsys_from_json_chunks(&chunks);
+    let (lsysv2,notes) = lsys_json_parse(&json2);
+    assert_eq!(notes, vec!());
 
     //println!("{}",json);
     //println!("{}",json2);
@@ -81,22 +76,510 @@ fn test_serde() {
     }
 }
 
+/*----------------------------------------------------------------------
+Helpers to run rules on plain strings
+*/
+
+#[cfg(test)]
+fn modules_to_string(ms:&[Module]) -> String {
+    let mut s = String::new();
+    for m in ms {
+        s.push_str(&m.sym);
+        if !m.params.is_empty() {
+            let params:Vec<String> = m.params.iter().map(|p| p.to_string()).collect();
+            s.push_str(&format!("({})", params.join(",")));
+        }
+    }
+    s
+}
+
+#[cfg(test)]
+fn apply(rules:&Rules, start:&str, order:i32, seed:u64, ignore:&str) -> String {
+    let lsys = LSys {
+        rules:  rules.clone(),
+        seed,
+        ignore: ignore.to_string(),
+        ..Default::default()
+    };
+    let start = modules_parse(&lsys, start).unwrap();
+    modules_to_string(&rules_apply_basic(&lsys, &lsys.rules, &start, order).unwrap())
+}
+
+#[cfg(test)]
+fn dacts_from_rules(lsys:&LSys, rules:&[Module]) -> (Vec<DAct>,BBox) {
+    let mut dacts = vec!();
+    let bbox = lsys_dacts(lsys, rules.iter().cloned(), &mut |dact| dacts.push(dact)).unwrap();
+    (dacts,bbox)
+}
+
 /*----------------------------------------------------------------------
 */
 
 #[test]
 fn test_rules_apply_basic() {
     let rules:Rules = HashMap::from([
-        ('A',"AB"),
-        ('B',"A")
+        ("A".to_string(),"AB".into()),
+        ("B".to_string(),"A".into())
     ]);
     let start:&str = "A";
 
-    assert_eq!(rules_apply_basic(&rules,start,0), "A");
-    assert_eq!(rules_apply_basic(&rules,start,1), "AB");
-    assert_eq!(rules_apply_basic(&rules,start,2), "ABA");
-    assert_eq!(rules_apply_basic(&rules,start,3), "ABAAB");
-    assert_eq!(rules_apply_basic(&rules,start,4), "ABAABABA");
+    assert_eq!(apply(&rules,start,0,0,""), "A");
+    assert_eq!(apply(&rules,start,1,0,""), "AB");
+    assert_eq!(apply(&rules,start,2,0,""), "ABA");
+    assert_eq!(apply(&rules,start,3,0,""), "ABAAB");
+    assert_eq!(apply(&rules,start,4,0,""), "ABAABABA");
+}
+
+/*----------------------------------------------------------------------
+Stochastic rules must be reproducible for a given seed, and every
+alternative with non-zero weight must eventually be chosen.
+*/
+
+#[test]
+fn test_rules_apply_stochastic() {
+    let rules:Rules = HashMap::from([
+        ("A".to_string(),Successor::Stochastic(vec![
+            (1.0,"B".to_string()),
+            (1.0,"C".to_string()),
+            (0.0,"D".to_string()),
+        ])),
+    ]);
+    let start = "A".repeat(200);
+
+    let s1 = apply(&rules,&start,1,7,"");
+    let s2 = apply(&rules,&start,1,7,"");
+    let s3 = apply(&rules,&start,1,8,"");
+    assert_eq!(s1, s2);
+    assert_ne!(s1, s3);
+    assert_eq!(s1.len(), 200);
+    assert!(s1.contains('B'));
+    assert!(s1.contains('C'));
+    assert!(!s1.contains('D'));
+
+    // stochastic form in json
+    let json = r#"{ "F" : [ [0.5, "F[+F]"], [0.5, "F[-F]"] ], "X" : "FX" }"#;
+    let rules:Rules = serde_json::from_str(json).unwrap();
+    assert_eq!(rules["X"], Successor::Basic("FX".to_string()));
+    assert!(matches!(rules["F"], Successor::Stochastic(_)));
+}
+
+/*----------------------------------------------------------------------
+Context matching, using the bracketed string from ABOP section 1.8,
+and signal propagation in both directions.
+*/
+
+#[test]
+fn test_rules_apply_context() {
+    let s = modules_parse(&LSys::default(), "ABC[DE][SG[HI[JK]L]MNO]").unwrap();
+    let i = s.iter().position(|m| &*m.sym == "S").unwrap();
+    let lhs = |l:&str| patterns_parse(&LSys::default(), l).unwrap();
+    assert!(context_left_match (&s, i, &lhs("BC"),      &[]).is_some());
+    assert!(context_right_match(&s, i, &lhs("G[H]M"),   &[]).is_some());
+    assert!(context_right_match(&s, i, &lhs("GM"),      &[]).is_some());
+    assert!(context_left_match (&s, i, &lhs("E"),       &[]).is_none());
+    assert!(context_right_match(&s, i, &lhs("GH"),      &[]).is_none());
+    assert!(context_right_match(&s, i, &lhs("G[H]MNOX"),&[]).is_none());
+
+    // acropetal signal, with ignored symbols in the way
+    let rules:Rules = HashMap::from([
+        ("b < a".to_string(),"b".into()),
+        ("b".to_string(),"a".into()),
+    ]);
+    let start = "ba+a[a]a-a";
+    assert_eq!(apply(&rules,start,1,0,"+-"), "ab+a[a]a-a");
+    assert_eq!(apply(&rules,start,2,0,"+-"), "aa+b[a]a-a");
+    assert_eq!(apply(&rules,start,3,0,"+-"), "aa+a[b]b-a");
+    assert_eq!(apply(&rules,start,4,0,"+-"), "aa+a[a]a-b");
+    // without ignore the signal stops at "+"
+    assert_eq!(apply(&rules,start,2,0,""),   "aa+a[a]a-a");
+
+    // basipetal signal
+    let rules:Rules = HashMap::from([
+        ("a > b".to_string(),"b".into()),
+        ("b".to_string(),"a".into()),
+    ]);
+    assert_eq!(apply(&rules,"aa[a]ab",1,0,""), "aa[a]ba");
+    assert_eq!(apply(&rules,"aa[a]ab",2,0,""), "ab[a]aa");
+
+    // badly formed rule keys are caught
+    assert!(rule_lhs_parse(&LSys::default(), "a<bc>d").is_err());
+    assert!(rule_lhs_parse(&LSys::default(), "a<>d").is_err());
+    // "<" and ">" are never symbols of a left hand side
+    for lhs in ["<", ">", "a > b > c", "a > b < c"] {
+        let why = rule_lhs_parse(&LSys::default(), lhs).err().unwrap();
+        assert!(why.contains("separate contexts"), "{lhs}: {why}");
+    }
+    let (left,pred,right,cond) = rule_lhs_parse(&LSys::default(), " a < b ").unwrap();
+    assert_eq!((left.len(),&*pred.sym,right.len(),cond), (1,"b",0,None));
+
+    // context rules, like titles, are escaped in the html
+    let mut lsys = lsys_json_parse(include_str!("lsys_examples.json")).0[0].clone();
+    lsys.title = "B(x)<A(t) & co -- 2".to_string();
+    lsys.rules.insert("X < Y".to_string(), "F".into());
+    let path = std::env::temp_dir().join(format!("rust_svg_escape_{}.html", std::process::id()));
+    let path = path.to_string_lossy().to_string();
+    lsys_draw_doc(&[lsys], &path, "A & B").unwrap();
+    let html = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert!(html.contains("<title>A &amp; B</title>"));
+    assert!(html.contains(">B(x)&lt;A(t) &amp; co -- 2</tspan>"));
+    assert!(html.contains(">X &lt; Y : F</tspan>"));
+    assert!(html.contains("<!-- begin page 1\n     B(x)<A(t) & co - 2 -->"));
+}
+
+/*----------------------------------------------------------------------
+Parametric rules, using the derivation from ABOP section 1.10.1
+*/
+
+#[test]
+fn test_rules_apply_parametric() {
+    let rules:Rules = HashMap::from([
+        ("A(x,y) : y <= 3".to_string(), "A(x*2,x+y)".into()),
+        ("A(x,y) : y > 3".to_string(),  "B(x)A(x/y,0)".into()),
+        ("B(x) : x < 1".to_string(),    "C".into()),
+        ("B(x) : x >= 1".to_string(),   "B(x-1)".into()),
+    ]);
+    let start = "B(2)A(4,4)";
+    assert_eq!(apply(&rules,start,1,0,""), "B(1)B(4)A(1,0)");
+    assert_eq!(apply(&rules,start,2,0,""), "B(0)B(3)A(2,1)");
+    assert_eq!(apply(&rules,start,3,0,""), "CB(2)A(4,3)");
+    assert_eq!(apply(&rules,start,4,0,""), "CB(1)A(8,7)");
+
+    // parameters bound from context, constants, rules without condition
+    let rules:Rules = HashMap::from([
+        ("B(x) < A(t) > C(y)".to_string(), "A(t+x*y*K)".into()),
+        ("A(t)".to_string(),                "A(0)".into()),
+    ]);
+    let lsys = LSys {
+        rules: rules.clone(),
+        consts: HashMap::from([("K".to_string(), 10.0)]),
+        ..Default::default()
+    };
+    let start = modules_parse(&lsys, "B(2)A(1)C(3)A(5)").unwrap();
+    let out = rules_apply_basic(&lsys, &lsys.rules, &start, 1).unwrap();
+    assert_eq!(modules_to_string(&out), "B(2)A(61)C(3)A(0)");
+
+    // errors are found when rules are checked
+    let check = |lhs:&str, rhs:&str| rules_check(
+        &LSys::default(), &HashMap::from([(lhs.to_string(), rhs.into())]));
+    assert!(check("A(t)", "F(t)").is_ok());
+    assert!(check("A(t)", "F(s)").is_err());
+    assert!(check("A(t) : s > 1", "F(t)").is_err());
+    assert!(check("A(2)", "F").is_err());
+    assert!(check("A(t)", "F(t").is_err());
+    assert!(check("A(t)", "F(sin(t,t))").is_err());
+}
+
+/*----------------------------------------------------------------------
+Table L-systems follow their schedule, then keep the last table
+*/
+
+#[test]
+fn test_rules_apply_tables() {
+    let mut lsys = LSys {
+        rules: HashMap::from([("A".to_string(), "AA".into())]),
+        tables: HashMap::from([
+            ("grow".to_string(),   HashMap::from([("A".to_string(), "AB".into())])),
+            ("flower".to_string(), HashMap::from([("B".to_string(), "C".into())])),
+        ]),
+        schedule: vec![("grow".to_string(),2), ("flower".to_string(),1)],
+        ..Default::default()
+    };
+    assert!(lsys_check(&lsys).is_ok());
+    let steps:Vec<&str> = (0..5).map(|i| lsys_table_at(&lsys,i)).collect();
+    assert_eq!(steps, ["grow","grow","flower","flower","flower"]);
+
+    let start = modules_parse(&lsys, "A").unwrap();
+    let apply = |lsys:&LSys, order| modules_to_string(&lsys_apply_tables(lsys,&start,order).unwrap());
+    assert_eq!(apply(&lsys,2), "ABB");
+    assert_eq!(apply(&lsys,4), "ACC");
+
+    // the main rules may be scheduled by name
+    lsys.schedule = vec![("rules".to_string(),1), ("grow".to_string(),1)];
+    assert_eq!(apply(&lsys,3), "ABBABB");
+    lsys.schedule.clear();
+    assert_eq!(apply(&lsys,2), "AAAA");
+
+    lsys.schedule = vec![("fruit".to_string(),1)];
+    assert!(lsys_check(&lsys).is_err());
+}
+
+/*----------------------------------------------------------------------
+Declared names are single symbols in every string: the start, rules,
+context, ignore and draw lists.  Undeclared they are split into chars.
+*/
+
+#[test]
+fn test_symbol_names() {
+    let mut lsys = LSys {
+        symbols: vec!["Apex".to_string(), "Internode".to_string()],
+        start: "Apex".to_string(),
+        draws: "Internode".to_string(),
+        ignore: "+-".to_string(),
+        rules: HashMap::from([
+            ("Apex".to_string(), "Internode[+Apex]Apex".into()),
+            ("Internode".to_string(), "Internode Internode".into()),
+        ]),
+        ..Default::default()
+    };
+    assert!(lsys_check(&lsys).is_ok());
+    let syms = |lsys:&LSys, s:&str| -> Vec<String> {
+        modules_parse(lsys, s).unwrap().iter().map(|m| m.sym.to_string()).collect()
+    };
+    assert_eq!(syms(&lsys, "ApexF(2)A"), ["Apex","F","A"]);
+    assert_eq!(syms(&LSys::default(), "ApexF"), ["A","p","e","x","F"]);
+
+    let start = modules_parse(&lsys, &lsys.start).unwrap();
+    let apply = |lsys:&LSys, order| modules_to_string(&lsys_apply_tables(lsys,&start,order).unwrap());
+    assert_eq!(apply(&lsys,2), "InternodeInternode[+Internode[+Apex]Apex]Internode[+Apex]Apex");
+    assert_eq!(growth::lsys_derived_length(&lsys,2).unwrap(), 17);
+    let acts = lsys_actions(&lsys);
+    assert!(actions_is_draw(&acts, "Internode") && actions_is_action(&acts, "Internode"));
+    assert!(!actions_is_draw(&acts, "Apex") && !actions_is_action(&acts, "Apex"));
+
+    // names in context and ignored symbols
+    lsys.rules = HashMap::from([("Apex < Internode".to_string(), "Apex".into())]);
+    let start = modules_parse(&lsys, "Apex+Internode").unwrap();
+    assert_eq!(modules_to_string(&lsys_apply_tables(&lsys,&start,1).unwrap()), "Apex+Apex");
+
+    lsys.symbols.push("A".to_string());
+    assert!(lsys_check(&lsys).is_err());
+    lsys.symbols = vec!["Ape x".to_string()];
+    assert!(lsys_check(&lsys).is_err());
+}
+
+/*----------------------------------------------------------------------
+Polygons record the start, explicit vertices, and ends of moves
+*/
+
+#[test]
+fn test_turtle_polygons() {
+    let lsys = LSys { angle: 90.0, ..Default::default() };
+    let polygons = |s:&str| {
+        let rules = modules_parse(&lsys, s).unwrap();
+        let (dacts,_) = dacts_from_rules(&lsys, &rules);
+        let mut out = vec!();
+        for dact in dacts {
+            if let DAct::Polygon(vs) = dact {
+                let vs:Vec<(i64,i64)> = vs.iter()
+                    .map(|(x,y)| (x.round() as i64, y.round() as i64))
+                    .collect();
+                out.push(vs);
+            }
+        }
+        out
+    };
+    // a unit square, drawn anticlockwise, so y goes up the page
+    let square = vec![vec![(0,0),(1,0),(1,-1),(0,-1)]];
+    assert_eq!(polygons("{f+f+f+f}"), square);
+    assert_eq!(polygons("{.F+F+F+F.}"), square);
+    // relative to where the turtle is at the close
+    assert_eq!(polygons("{f+f}"), vec![vec![(-1,1),(0,1),(0,0)]]);
+    // nested, with too few vertices to fill dropped
+    assert_eq!(polygons("{f{f}+f}").len(), 1);
+    assert_eq!(polygons("F[{f+f}]F").len(), 1);
+    // "G" and "g" leave the polygon alone
+    assert_eq!(polygons("{f+f+f+f+g+g+g+g}"), square);
+    assert_eq!(polygons("{.G+G+G+G.}").len(), 0);
+    // extra symbols act like "F" and "f"
+    let lsys = LSys { draws: "A".to_string(), moves: "a".to_string(), ..lsys.clone() };
+    let bbox = |lsys:&LSys, s:&str| {
+        let rules = modules_parse(lsys, s).unwrap();
+        dacts_from_rules(lsys, &rules).1
+    };
+    assert_eq!(bbox(&lsys, "A+a+A"), bbox(&lsys, "F+f+F"));
+}
+
+/*----------------------------------------------------------------------
+Color and width.  Styles are only reported before something is drawn,
+and are restored at the end of a branch.
+*/
+
+#[test]
+fn test_turtle_styles() {
+    let lsys = LSys { angle: 90.0, ..Default::default() };
+    let styles = |s:&str| {
+        let rules = modules_parse(&lsys, s).unwrap();
+        let (dacts,_) = dacts_from_rules(&lsys, &rules);
+        let mut out = vec!();
+        for dact in dacts {
+            if let DAct::Style(c,w) = dact {
+                out.push((c,w));
+            }
+        }
+        out
+    };
+    assert_eq!(styles("F+F"), vec!());
+    assert_eq!(styles("'F'F"), vec![(1,1.0),(2,1.0)]);
+    assert_eq!(styles("!F!F#F"), vec![(0,WIDTH_FACTOR),(0,WIDTH_FACTOR*WIDTH_FACTOR),(0,1.0)]);
+    assert_eq!(styles("#(4)F'(3)!(2)F"), vec![(0,4.0),(3,2.0)]);
+    // no drawing, no style
+    assert_eq!(styles("'f#f"), vec!());
+    assert_eq!(styles("F['#(2)F]F"), vec![(1,2.0),(0,1.0)]);
+
+    // a trunk thinning by the width factor of the LSys, never to nothing
+    let thin = LSys { width_factor: 0.5, ..lsys.clone() };
+    let rules = modules_parse(&thin, "#(4)F!F!F!F").unwrap();
+    let widths:Vec<f64> = dacts_from_rules(&thin, &rules).0.iter()
+        .filter_map(|dact| if let DAct::Style(_,w) = dact { Some(*w) } else { None })
+        .collect();
+    assert_eq!(widths, vec![4.0, 2.0, 1.0, 0.5]);
+
+    // colors from the LSys, or the defaults, wrapping around
+    let mut lsys = lsys.clone();
+    assert_eq!(lsys_color(&lsys, 1), DEFAULT_COLORS[1]);
+    lsys.colors = vec!["red".to_string(), "blue".to_string()];
+    assert_eq!(lsys_color(&lsys, 3), "blue");
+}
+
+/*----------------------------------------------------------------------
+Line length.  Scaling the length by ">" and "<" must draw the same tree
+as passing the length as a parameter, and branches restore it.
+*/
+
+#[test]
+fn test_turtle_length() {
+    let lsys = LSys { angle: 85.0, length_factor: 0.5, ..Default::default() };
+    let bbox = |s:&str| {
+        let rules = modules_parse(&lsys, s).unwrap();
+        dacts_from_rules(&lsys, &rules).1
+    };
+    assert_eq!(bbox("F>F>F"), bbox("F(1)F(0.5)F(0.25)"));
+    assert_eq!(bbox("F[>>F]F"), bbox("F(2)"));
+    assert_eq!(bbox("F<F>(3)F"), bbox("F(9)"));
+
+    let tree = LSys {
+        angle: 85.0,
+        start: "+(90)A".to_string(),
+        length_factor: 1.0/1.456,
+        rules: HashMap::from([("A".to_string(), "F[+>A][->A]".into())]),
+        ..Default::default()
+    };
+    let parametric = LSys {
+        start: "+(90)A(1)".to_string(),
+        length_factor: 0.0,
+        rules: HashMap::from([
+            ("A(s)".to_string(), "F(s)[+A(s/1.456)][-A(s/1.456)]".into()),
+        ]),
+        ..tree.clone()
+    };
+    let bbox = |lsys:&LSys| {
+        let (_,(x0,y0,x1,y1)) = dacts_from_rules(lsys, &lsys_apply_rules(lsys,6).unwrap());
+        [x0,y0,x1,y1].map(|v| (v * 1e6).round())
+    };
+    assert_eq!(bbox(&tree), bbox(&parametric));
+}
+
+/*----------------------------------------------------------------------
+3D turtle and output.  The 3D Hilbert curve must visit every point of
+a cube once, in unit steps, and its tubes must make a closed mesh.
+*/
+
+#[test]
+fn test_turtle_3d() {
+    let lsys = LSys {
+        angle: 90.0,
+        start: "A".to_string(),
+        rules: HashMap::from([
+            ("A".to_string(), "B-F+CFC+F-D&F^D-F+&&CFC+F+B//".into()),
+            ("B".to_string(), "A&F^CFB^F^D^^-F-D^|F^B|FC^F^A//".into()),
+            ("C".to_string(), "|D^|F^B-F+C^F^A&&FA&F^C+F+B^F^D//".into()),
+            ("D".to_string(), "|CFB-F+B|FA&F^A&&FB-F+B|FC//".into()),
+        ]),
+        ..Default::default()
+    };
+    for (order,side) in [(1,2),(2,4),(3,8)] {
+        let lines = mesh::lsys_polylines_3d(&lsys, order).unwrap();
+        assert_eq!(lines.len(), 1);
+        let mut points:Vec<[i64;3]> = lines[0].iter()
+            .map(|p| p.map(|x| x.round() as i64))
+            .collect();
+        for (p,q) in lines[0].iter().zip(&points) {
+            assert!((0..3).all(|k| (p[k] - q[k] as f64).abs() < 1e-9));
+        }
+        points.sort();
+        points.dedup();
+        assert_eq!(points.len(), side * side * side);
+        for k in 0..3 {
+            let lo = points.iter().map(|p| p[k]).min().unwrap();
+            let hi = points.iter().map(|p| p[k]).max().unwrap();
+            assert_eq!(hi - lo, side as i64 - 1);
+        }
+    }
+
+    // after rolling to horizontal, turning left stays level
+    let end_y = |start:&str| {
+        let lsys = LSys { start: start.to_string(), ..Default::default() };
+        mesh::lsys_polylines_3d(&lsys, 0).unwrap()[0][1][1]
+    };
+    assert!(end_y("+(30)^(40)/(25)+(90)F").abs() > 0.1);
+    assert!(end_y("+(30)^(40)/(25)$+(90)F").abs() < 1e-9);
+
+    // each edge of a closed mesh is used once in each direction
+    let lines = vec![vec![[0.0,0.0,0.0], [1.0,0.0,0.0], [1.0,2.0,0.0]]];
+    let mesh = mesh::mesh_tubes(&lines, 0.1, 6);
+    assert_eq!(mesh.triangles.len(), 2 * 4 * 6);
+    let mut edges:Vec<(usize,usize)> = mesh.triangles.iter()
+        .flat_map(|t| [(t[0],t[1]), (t[1],t[2]), (t[2],t[0])])
+        .collect();
+    edges.sort();
+    let mut reversed:Vec<(usize,usize)> = edges.iter().map(|&(a,b)| (b,a)).collect();
+    reversed.sort();
+    assert_eq!(edges, reversed);
+
+    let stl = mesh::stl_binary_from_mesh("test", &mesh);
+    assert_eq!(stl.len(), 84 + 50 * mesh.triangles.len());
+    let stl = mesh::stl_ascii_from_mesh("test mesh", &mesh);
+    assert!(stl.starts_with("solid test_mesh\n"));
+    assert_eq!(stl.matches("facet normal").count(), mesh.triangles.len());
+    let obj = mesh::obj_from_polylines("test", &lines);
+    assert!(obj.ends_with("l 1 2 3\n"));
+
+    // each of them from the command line
+    use cli::{cli_parse, cli_lsys, cli_run};
+    let path = std::env::temp_dir().join(format!("rust_svg_3d_{}", std::process::id()));
+    let path = path.to_string_lossy().to_string();
+    let written = |options:&str| {
+        let args:Vec<String> = format!("-t 3D -o {path} {options}").split_whitespace().map(String::from).collect();
+        let opts = cli_parse(&args).unwrap();
+        cli_run(&opts, &cli_lsys(&opts).unwrap().0).unwrap();
+        std::fs::read(&path).unwrap()
+    };
+    let obj = String::from_utf8(written("-f obj")).unwrap();
+    assert!(obj.contains("\nl 1 2 ") && !obj.contains("\nf "));
+    let obj = String::from_utf8(written("-f obj --tubes")).unwrap();
+    assert!(obj.contains("\nf ") && !obj.contains("\nl "));
+    let stl = String::from_utf8(written("-f stl --ascii")).unwrap();
+    assert!(stl.starts_with("solid Hilbert_Curve_3D\n") && stl.contains("facet normal"));
+    let stl = written("-f stl");
+    assert!(stl.starts_with(b"binary STL: "));
+    let triangles = u32::from_le_bytes(stl[80..84].try_into().unwrap()) as usize;
+    assert_eq!(stl.len(), 84 + 50 * triangles);
+    std::fs::remove_file(&path).unwrap();
+}
+
+/*----------------------------------------------------------------------
+Expression parsing and evaluation
+*/
+
+#[test]
+fn test_expr() {
+    let names = vec!["x".to_string(), "y".to_string()];
+    let consts = HashMap::from([("R".to_string(), 2.0)]);
+    let eval = |src:&str| expr_eval(&expr_parse(src, &names, &consts).unwrap(), &[3.0, 4.0]);
+    assert_eq!(eval("1 + 2 * 3"),         7.0);
+    assert_eq!(eval("(1 + 2) * 3"),       9.0);
+    assert_eq!(eval("-2^2"),             -4.0);
+    assert_eq!(eval("2^3^2"),           512.0);
+    assert_eq!(eval("x*y/R"),             6.0);
+    assert_eq!(eval("1.5e1 % 4"),         3.0);
+    assert_eq!(eval("x < y && y < 5"),    1.0);
+    assert_eq!(eval("x > y || !(x = 3)"), 0.0);
+    assert_eq!(eval("max(x, sqrt(y))"),   3.0);
+    assert!((eval("sin(30)") - 0.5).abs() < 1e-12);
+    for bad in ["", "1 +", "(1", "z", "foo(1)", "1 2", "x $ y"] {
+        assert!(expr_parse(bad, &names, &consts).is_err(), "{bad}");
+    }
 }
 
 /*----------------------------------------------------------------------
@@ -104,9 +587,275 @@ fn test_rules_apply_basic() {
 
 #[test]
 fn test_rules_minimize() {
-    assert_eq!(rules_minimize("ABCD"),            ""      );
-    assert_eq!(rules_minimize(ACTIONS),           ACTIONS );
-    assert_eq!(rules_minimize("AFBfC+D-E[G]H|I"), ACTIONS );
+    let lsys = LSys::default();
+    let minimize = |lsys:&LSys, s:&str| modules_to_string(&rules_minimize(
+        lsys, &modules_parse(&LSys::default(), s).unwrap()));
+    assert_eq!(minimize(&lsys, "ABCD"),            ""         );
+    assert_eq!(minimize(&lsys, ACTIONS),           ACTIONS    );
+    assert_eq!(minimize(&lsys, "AFBfC+D-E[G]H|I"), "Ff+-[G]|" );
+    assert_eq!(minimize(&lsys, "A(1)F(2)B+(30)"),  "F(2)+(30)");
+
+    // extra draw and move symbols are kept
+    let lsys = LSys { draws: "AB".to_string(), moves: "C".to_string(), ..lsys };
+    assert_eq!(minimize(&lsys, "ABCD"),            "ABC"      );
+    assert!(lsys_check(&lsys).is_ok());
+    assert!(lsys_check(&LSys { moves: "f".to_string(), ..lsys.clone() }).is_err());
+    assert!(lsys_check(&LSys { moves: "A".to_string(), ..lsys.clone() }).is_err());
+}
+
+/*----------------------------------------------------------------------
+Lazy derivation must give the same modules as the full string, for all
+the examples, including stochastic and context sensitive ones.  Large
+orders must work without building the string.
+*/
+
+#[test]
+fn test_lsys_derive() {
+    fn sendable<T:Send + Sync>(_:&T) {}
+    sendable(&lsys_apply_rules(&LSys::default(), 1).unwrap());
+    for lsys in lsys_json_parse(include_str!("lsys_examples.json")).0 {
+        for order in 0..4 {
+            let lazy:Vec<Module> = lsys_derive(&lsys, order).unwrap().collect();
+            assert_eq!(lazy, lsys_apply_rules(&lsys, order).unwrap(), "{} {order}", lsys.title);
+        }
+    }
+
+    // the Dragon curve draws 2^n segments
+    let dragon = LSys {
+        angle: 90.0,
+        start: "+FX".to_string(),
+        rules: HashMap::from([
+            ("X".to_string(), "X+YF+".into()),
+            ("Y".to_string(), "-FX-Y".into()),
+        ]),
+        ..Default::default()
+    };
+    let count = lsys_derive(&dragon, 16).unwrap().filter(|m| &*m.sym == "F").count();
+    assert_eq!(count, 1 << 16);
+
+    // a long chain, stochastic and scheduled, keeps nothing for the steps it has passed
+    let chain = LSys {
+        start: "A".to_string(),
+        rules: HashMap::from([("A".to_string(), Successor::Stochastic(vec![(1.0, "A".to_string())]))]),
+        post_rules: HashMap::from([("A".to_string(), "F".into())]),
+        tables: HashMap::from([("t".to_string(), HashMap::from([("A".to_string(), "A".into())]))]),
+        schedule: vec![("rules".to_string(), 3), ("t".to_string(), 2)],
+        ..Default::default()
+    };
+    let modules:Vec<Module> = lsys_derive(&chain, 100_000).unwrap().collect();
+    assert_eq!(modules_to_string(&modules), "F");
+    assert_eq!(lsys_derive(&LSys { start: String::new(), ..chain }, 5).unwrap().count(), 0);
+}
+
+/*----------------------------------------------------------------------
+Growth of D0L systems.  Counts and predicted segments must agree with
+derivation for all the D0L examples, and the growth rate of the
+Fibonacci system is the golden ratio.
+*/
+
+#[test]
+fn test_growth() {
+    let mut d0l = 0;
+    for lsys in lsys_json_parse(include_str!("lsys_examples.json")).0 {
+        let Ok(growth) = growth::growth_new(&lsys) else {
+            continue;
+        };
+        d0l += 1;
+        for order in 0..4 {
+            let acts = lsys_actions(&lsys);
+            let drawn = lsys_derive(&lsys, order).unwrap().filter(|m| actions_is_draw(&acts, &m.sym)).count();
+            assert_eq!(growth::growth_segments(&lsys, &growth, order), Some(drawn as u128),
+                "{} {order}", lsys.title);
+        }
+    }
+    assert!(d0l > 10);
+
+    let fibonacci = LSys {
+        start: "A".to_string(),
+        rules: HashMap::from([
+            ("A".to_string(), "AB".into()),
+            ("B".to_string(), "A".into()),
+        ]),
+        ..Default::default()
+    };
+    let growth = growth::growth_new(&fibonacci).unwrap();
+    assert_eq!(growth.symbols, vec![Sym::from("A"), Sym::from("B")]);
+    assert_eq!(growth::growth_counts(&fibonacci, &growth, 10), Some(vec![89,55]));
+    let phi = (1.0 + 5.0_f64.sqrt()) / 2.0;
+    assert!((growth::growth_rate(&fibonacci, &growth) - phi).abs() < 1e-9);
+    // too big even for u128
+    assert_eq!(growth::growth_counts(&fibonacci, &growth, 200), None);
+    assert_eq!(growth::lsys_segments_predict(&fibonacci, 10), Some(0));
+
+    // a period of two still settles
+    let swap = LSys {
+        start: "A".to_string(),
+        rules: HashMap::from([
+            ("A".to_string(), "BBBB".into()),
+            ("B".to_string(), "A".into()),
+        ]),
+        ..Default::default()
+    };
+    let growth = growth::growth_new(&swap).unwrap();
+    assert!((growth::growth_rate(&swap, &growth) - 2.0).abs() < 1e-9);
+
+    // not D0L
+    let cond = LSys {
+        start: "A(1)".to_string(),
+        rules: HashMap::from([("A(x) : x < 3".to_string(), "A(x+1)".into())]),
+        ..Default::default()
+    };
+    assert!(growth::growth_new(&cond).is_err());
+    assert_eq!(growth::lsys_segments_predict(&cond, 3), None);
+
+    // a module with other parameters than its rule is not rewritten
+    let mismatch = LSys {
+        start: "A(1)".to_string(),
+        rules: HashMap::from([("A(x,y)".to_string(), "A(x,y)F".into())]),
+        ..Default::default()
+    };
+    assert!(matches!(growth::growth_new(&mismatch), Err(LSysError::NotD0L(_))));
+    let start = modules_parse(&mismatch, &mismatch.start).unwrap();
+    assert_eq!(lsys_apply_tables(&mismatch, &start, 3).unwrap(), start);
+
+    // only the tables the schedule uses need be D0L
+    let tables = LSys {
+        start: "F".to_string(),
+        rules: HashMap::from([("F".to_string(), Successor::Stochastic(vec![(1.0, "F".to_string()), (1.0, "FF".to_string())]))]),
+        tables: HashMap::from([("grow".to_string(), HashMap::from([("F".to_string(), "FF".into())]))]),
+        schedule: vec![("grow".to_string(), 1)],
+        ..Default::default()
+    };
+    assert_eq!(growth::lsys_segments_predict(&tables, 5), Some(32));
+    assert_eq!(lsys_derive(&tables, 5).unwrap().count(), 32);
+
+    // orders are refused before deriving when too high or too big
+    let line = LSys {
+        start: "F".to_string(),
+        rules: HashMap::from([("F".to_string(), "F".into())]),
+        ..Default::default()
+    };
+    assert!(lsys_order_check(&line, ORDER_MAX).is_ok());
+    assert!(matches!(lsys_order_check(&line, 2_000_000_000), Err(LSysError::OrderTooHigh { .. })));
+    assert!(matches!(lsys_order_check(&fibonacci, 40), Err(LSysError::TooManyModules { .. })));
+    let dragon = LSys {
+        start: "FX".to_string(),
+        rules: HashMap::from([
+            ("X".to_string(), "X+YF+".into()),
+            ("Y".to_string(), "-FX-Y".into()),
+        ]),
+        ..Default::default()
+    };
+    assert!(matches!(lsys_order_check(&dragon, 30), Err(LSysError::TooManySegments { .. })));
+    let bloat = LSys {
+        start: "A(1)".to_string(),
+        rules: HashMap::from([("A(x) : x > 0".to_string(), "A(x)+A(x)".into())]),
+        ..Default::default()
+    };
+    assert!(growth::growth_new(&bloat).is_err());
+    assert!(order_check_within(&bloat, 10, 2000, 2000).is_ok());
+    assert!(matches!(order_check_within(&bloat, 11, 2000, 2000), Err(LSysError::TooManyModules { .. })));
+}
+
+/*----------------------------------------------------------------------
+Random access must agree with the full string, for all the D0L
+examples, and reach into strings far too long to derive.
+*/
+
+#[test]
+fn test_derived_slice() {
+    for lsys in lsys_json_parse(include_str!("lsys_examples.json")).0 {
+        if growth::growth_new(&lsys).is_err() {
+            continue;
+        }
+        let start = modules_parse(&lsys, &lsys.start).unwrap();
+        for order in 0..4 {
+            let full = lsys_apply_tables(&lsys, &start, order).unwrap();
+            let len = full.len() as u128;
+            assert_eq!(growth::lsys_derived_length(&lsys, order).unwrap(), len);
+            let all = growth::lsys_derived_slice(&lsys, order, 0, full.len()).unwrap();
+            assert_eq!(all, full, "{} {order}", lsys.title);
+            let window = growth::lsys_derived_slice(&lsys, order, len/3, 5).unwrap();
+            assert_eq!(window, full.iter().skip(full.len()/3).take(5).cloned().collect::<Vec<_>>());
+            assert_eq!(growth::lsys_derived_at(&lsys, order, len).unwrap(), None);
+        }
+    }
+
+    // each order of the Dragon curve begins with the one before
+    let dragon = LSys {
+        start: "+FX".to_string(),
+        rules: HashMap::from([
+            ("X".to_string(), "X+YF+".into()),
+            ("Y".to_string(), "-FX-Y".into()),
+        ]),
+        ..Default::default()
+    };
+    let small = modules_to_string(&growth::lsys_derived_slice(&dragon, 10, 1000, 20).unwrap());
+    let large = modules_to_string(&growth::lsys_derived_slice(&dragon, 100, 1000, 20).unwrap());
+    assert_eq!(small, large);
+    assert!(growth::lsys_derived_length(&dragon, 100).unwrap() > 1 << 100);
+    assert!(growth::lsys_derived_at(&dragon, ORDER_MAX, 1 << 100).unwrap().is_some());
+
+    // one level of descent for each step, so the order is capped
+    let fibonacci = LSys {
+        start: "A".to_string(),
+        rules: HashMap::from([("A".to_string(), "AB".into()), ("B".to_string(), "A".into())]),
+        ..Default::default()
+    };
+    assert!(matches!(growth::lsys_derived_at(&fibonacci, 1_000_000, 0),
+        Err(LSysError::OrderTooHigh { order: 1_000_000 })));
+}
+
+/*----------------------------------------------------------------------
+Automatic orders.  The first box shows the simplest figure, later boxes
+show higher orders, and any orders given are used for the last boxes.
+*/
+
+#[test]
+fn test_lsys_orders() {
+    let lb = layout_boxes_make();
+    let koch = LSys {
+        angle: 60.0,
+        start: "+F--F--F".to_string(),
+        rules: HashMap::from([("F".to_string(), "F+F--F+F".into())]),
+        ..Default::default()
+    };
+    let orders = lsys_orders(&koch, &lb).unwrap();
+    assert_eq!(orders[0], 0);
+    assert!(orders.windows(2).all(|w| w[0] < w[1]));
+    assert!(3 * 4_usize.pow(orders[3] as u32) * SEGMENT_AREA as usize
+        <= (PAGE_WIDTH * PAGE_HEIGHT) as usize);
+
+    // nothing drawn until "X" has grown into "F"
+    let late = LSys {
+        start: "X".to_string(),
+        rules: HashMap::from([
+            ("X".to_string(), "Y".into()),
+            ("Y".to_string(), "F+F".into()),
+            ("F".to_string(), "FF".into()),
+        ]),
+        ..koch.clone()
+    };
+    assert_eq!(lsys_orders(&late, &lb).unwrap()[0], 2);
+
+    // given orders, all or some
+    let given = |order:Vec<i32>| lsys_orders(&LSys { order, ..koch.clone() }, &lb).unwrap();
+    assert_eq!(given(vec![1,2,3,4,5]), vec![1,2,3,4]);
+    assert_eq!(given(vec![2,3,4,5]),   vec![2,3,4,5]);
+    assert_eq!(given(vec![1]),         vec![0,1]);
+    assert_eq!(given(vec![2]),         vec![0,1,2]);
+    assert_eq!(given(vec![0]),         vec![0]);
+    assert_eq!(given(vec![2,5,6]),     vec![0,2,5,6]);
+    // one order given, with the others distinct and below it
+    for k in 1..9 {
+        let orders = given(vec![k]);
+        assert_eq!(orders.len(), (k as usize + 1).min(4), "{orders:?}");
+        assert!(orders.windows(2).all(|w| w[0] < w[1]) && orders[orders.len()-1] == k);
+    }
+
+    // a figure which never grows is not derived to AUTO_ORDER_MAX
+    let fixed = LSys { rules: HashMap::new(), ..koch.clone() };
+    assert_eq!(lsys_orders(&fixed, &lb).unwrap(), vec![0,1,2,3]);
 }
 
 /*----------------------------------------------------------------------
@@ -119,11 +868,671 @@ fn test_layout_boxes() {
     //print!("bounding boxes{:#?}",&lb);
     let mut ds = doc_new();
     doc(&mut ds, DocAct::DocOpenPathTitle(
-        &"layout_boxes.html",
-        &"test_layout_boxes",
-    ));
-    doc(&mut ds, DocAct::PageStartComment(&""));
-    doc(&mut ds, DocAct::PageAddFragment(&svg));
-    doc(&mut ds, DocAct::PageEnd);
-    doc(&mut ds, DocAct::DocClose);
+        "layout_boxes.html",
+        "test_layout_boxes",
+    )).unwrap();
+    doc(&mut ds, DocAct::PageStartComment("")).unwrap();
+    doc(&mut ds, DocAct::PageAddFragment(&svg)).unwrap();
+    doc(&mut ds, DocAct::PageEnd).unwrap();
+    doc(&mut ds, DocAct::DocClose).unwrap();
+}
+
+/*----------------------------------------------------------------------
+Errors are returned, not panics, so a bad LSys can be skipped.
+*/
+
+#[test]
+fn test_errors() {
+    let lsys = LSys::default();
+    let turtle = |s:&str| {
+        let rules = modules_parse(&lsys, s).unwrap();
+        lsys_dacts(&lsys, rules, &mut |_| ())
+    };
+    assert!(turtle("F[+F]F{f+f+f}").is_ok());
+    assert!(matches!(turtle("F[+F]]F"), Err(LSysError::Unbalanced { bracket: ']', pos: 5 })));
+    assert!(matches!(turtle("{f+f}}"), Err(LSysError::Unbalanced { bracket: '}', pos: 5 })));
+    assert!(matches!(turtle("FAF"), Err(LSysError::UnknownSymbol(s)) if s == "A"));
+
+    // rules which do not parse, found without lsys_check
+    let bad = LSys { start: "F(".to_string(), ..Default::default() };
+    assert!(matches!(lsys_derive(&bad, 1), Err(LSysError::Invalid(_))));
+    let bad = LSys { schedule: vec![("fruit".to_string(),1)], ..Default::default() };
+    assert!(matches!(lsys_apply_rules(&bad, 1), Err(LSysError::Invalid(_))));
+    let stochastic = LSys {
+        rules: HashMap::from([("A".to_string(), Successor::Stochastic(vec![]))]),
+        ..Default::default()
+    };
+    assert!(matches!(growth::growth_new(&stochastic), Err(LSysError::Invalid(_))));
+    let cond = LSys {
+        rules: HashMap::from([("A : 1 > 0".to_string(), "AA".into())]),
+        ..Default::default()
+    };
+    assert!(matches!(growth::growth_new(&cond), Err(LSysError::NotD0L(_))));
+
+    // a page without its layout boxes
+    let mut lb = layout_boxes_make();
+    lb.remove("main");
+    assert!(matches!(lsys_orders(&lsys, &lb), Err(LSysError::MissingBox(b)) if b == "main"));
+
+    // a page which can not be drawn is left out, and an order too high
+    // leaves its box empty, the page kept
+    let examples = lsys_json_parse(include_str!("lsys_examples.json")).0;
+    let bad = LSys { title: "Bad".to_string(), start: "F]".to_string(), ..examples[0].clone() };
+    let high = LSys { order: vec![1, 30], ..examples[1].clone() };
+    let path = std::env::temp_dir().join(format!("rust_svg_skipped_{}.html", std::process::id()));
+    let path = path.to_string_lossy().to_string();
+    let skipped = lsys_draw_doc(&[bad, high], &path, "Test").unwrap();
+    assert!(matches!(&skipped[..], [(title, LSysError::Unbalanced { .. }),
+        (_, LSysError::TooManySegments { order: 30, .. })] if title == "Bad"));
+    let html = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert!(html.contains("begin page 1") && !html.contains("begin page 2"));
+    assert!(!html.contains("Bad"));
+
+    // document actions out of order, and files which can not be opened
+    let mut ds = doc_new();
+    assert!(matches!(doc(&mut ds, DocAct::PageEnd), Err(LSysError::Doc(_))));
+    let r = doc(&mut ds, DocAct::DocOpenPathTitle("no/such/dir/x.html", ""));
+    assert!(matches!(r, Err(LSysError::Io(..))));
+}
+
+/*----------------------------------------------------------------------
+Regular expressions for titles, and command line options
+*/
+
+#[test]
+fn test_regex() {
+    use regex::{regex_parse, regex_find};
+    let find = |re:&str, text:&str| regex_find(&regex_parse(re).unwrap(), text);
+    assert!(find("Curve", "Hilbert Curve 3D"));
+    assert!(!find("^Curve", "Hilbert Curve 3D"));
+    assert!(find("^(Hilbert|Dragon) C", "Dragon Curve"));
+    assert!(!find("^(Hilbert|Dragon) C", "Peano Curve aka Hilbert II"));
+    assert!(find("3D$", "Hilbert Curve 3D"));
+    assert!(find("P[a-e]+no", "Peano"));
+    assert!(!find("[^A-Z]ea", "Peano"));
+    assert!(find("\\d\\s*D", "Curve 3 D"));
+    assert!(find("a.*b?c+$", "a-c"));
+    assert!(find("^x{2,3}$", "xxx") && !find("^x{2,3}$", "xxxx"));
+    assert!(find("(a*)+$", ""));
+    assert!(find("\\(\\)", "f()"));
+    assert!(find("^(ab|a)(bc|c)$", "abc") && find("^a{0,2}b?$", "aab") && !find("^a{0,2}$", "aaa"));
+    // nested repeats, which take exponential time to backtrack
+    let many = "a".repeat(10_000);
+    assert!(!find("^(a*)*b", &many) && !find("(a|a)*(a|aa)*b", &many));
+    assert!(find("^(a*)*$", &many) && find("(a+)+a{3}$", &many));
+    for bad in ["(a", "a)", "[ab", "*a", "a{2", "a{3,1}", "a{1001}", "\\", "x{99999999,}", "((a{1000}){1000}){1000}"] {
+        assert!(regex_parse(bad).is_err(), "{bad}");
+    }
+}
+
+#[test]
+fn test_cli() {
+    use cli::{cli_parse, cli_lsys, cli_run, Format};
+    let parse = |s:&str| {
+        let args:Vec<String> = s.split_whitespace().map(String::from).collect();
+        cli_parse(&args)
+    };
+    let opts = parse("a.json - -o out.svg --title=koch -n 1,2 -1").unwrap();
+    assert_eq!(opts.inputs, ["a.json", "-"]);
+    assert_eq!(opts.output.as_deref(), Some("out.svg"));
+    assert_eq!(opts.title.as_deref(), Some("koch"));
+    assert_eq!(opts.orders, Some(vec![1,2]));
+    assert_eq!(opts.format, Format::Svg);
+    assert!(opts.figure && !opts.help);
+    assert_eq!(parse("-o x.svg -f html").unwrap().format, Format::Html);
+    assert_eq!(parse("").unwrap().format, Format::Html);
+    assert_eq!(parse("-o all.lsys").unwrap().format, Format::Lsys);
+    assert_eq!(parse("-o all.PDF").unwrap().format, Format::Pdf);
+    let png = parse("-o a.png --dpi 192 --stroke-width=3 --background none --aliased").unwrap().png;
+    assert_eq!((png.dpi, png.stroke_width, png.background, png.antialias), (192.0, 3.0, None, false));
+    assert_eq!(parse("--background #f00").unwrap().png.background, Some([255, 0, 0]));
+    let plot = parse("-o a.gcode --size 100,50 --feed=500,2000 --pen-up M5 --in-order").unwrap().plot;
+    assert_eq!((plot.width, plot.height, plot.draw_feed, plot.travel_feed), (100.0, 50.0, 500.0, 2000.0));
+    assert_eq!((plot.pen_up.as_str(), plot.pen_down.as_str(), plot.reorder), ("M5", "G0 Z0", false));
+    assert_eq!(parse("-o a.hpgl").unwrap().format, Format::Hpgl);
+    for bad in ["-o", "-x", "-n 1,-2", "-n 1,101", "-n 1,2,3,4,5", "-f gif", "-m (a", "--dpi 5", "--dpi 1200", "--background nocolor",
+        "--size 100", "--feed 0,10", "-m x{99999999,}", "-m ((a{1000}){1000}){1000}"] {
+        assert!(matches!(parse(bad), Err(LSysError::Usage(_))), "{bad}");
+    }
+
+    // the built in examples, chosen by title and with orders replaced
+    let chosen = |s:&str| cli_lsys(&parse(s).unwrap()).unwrap().0;
+    assert_eq!(chosen("").len(), lsys_json_parse(cli::EXAMPLES).0.len());
+    let (_,reports) = cli_lsys(&parse("-t DRAGON").unwrap()).unwrap();
+    assert_eq!(reports.len(), 1);
+    assert_eq!(reports[0].loaded, chosen("").len());
+    assert_eq!(reports[0].to_string(),
+        format!("Successfully loaded {} of {} LSys from lsys_examples.json", reports[0].loaded, reports[0].loaded));
+    let dragon = chosen("-t DRAGON -n 2");
+    assert_eq!(dragon.len(), 1);
+    assert_eq!(dragon[0].order, vec![2]);
+    assert_eq!(chosen("-m ^Sierpinski -t square").len(), 1);
+    assert!(matches!(cli_lsys(&parse("no/such.json").unwrap()), Err(LSysError::Io(..))));
+
+    // a file is only replaced once all of it is drawn
+    let path = std::env::temp_dir().join(format!("rust_svg_keep_{}.obj", std::process::id()));
+    let path = path.to_string_lossy().to_string();
+    std::fs::write(&path, "keep").unwrap();
+    let opts = parse(&format!("-t DRAGON -n 30 -o {path}")).unwrap();
+    assert!(matches!(cli_run(&opts, &chosen("-t DRAGON -n 30")), Err(LSysError::TooManySegments { .. })));
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "keep");
+    assert!(!std::path::Path::new(&format!("{path}.part")).exists());
+    std::fs::remove_file(&path).unwrap();
+}
+
+/*----------------------------------------------------------------------
+Json files with arrays, JSON Lines and objects one after another, and
+the notes for what is skipped.
+*/
+
+#[test]
+fn test_json_load() {
+    let json = indoc! {r#"
+        # comments are not noted
+        [{"title": "A", "start": "F", R}, {"title": "B", "start": "F", R}]
+        {"title": "C", "start": "F", R}
+        {"title": "D", "start": "F", R}{"title": "E", "start": "F", R}
+        stray text
+        {"title": "F", "start": "F", R,
+         "angle": }
+        {"title": "G", "start": "F", R}
+        {"title": "H", "colors": 3, "start": "F", R}
+        {"title": "I", "start": "F(", R}
+        [1, {"title": "J", "start": "F", R} {"title": "K", "start": "F", R}]
+        {"title": "L", "start": "F", R}
+    "#}.replace('R', r#""refs": [], "angle": 90, "order": [], "rules": {}, "post_rules": {}"#);
+    let (lsysv,notes) = lsys_json_parse(&json);
+    let titles:Vec<&str> = lsysv.iter().map(|l| l.title.as_str()).collect();
+    assert_eq!(titles, ["A", "B", "C", "D", "E", "G", "J", "L"]);
+
+    let places:Vec<(usize,usize,bool)> = notes.iter()
+        .map(|n| (n.line, n.column, n.error)).collect();
+    assert_eq!(places, [
+        (5, 1, false),      // stray text
+        (7, 11, true),      // syntax error, then on to G
+        (9, 26, true),      // colors is not a list
+        (10, 1, true),      // start does not check
+        (11, 2, false),     // 1 is not an object
+        (11, 103, true),    // missing ',', then on to L
+    ]);
+    assert!(notes[0].text.contains("stray text"));
+    assert!(notes[3].text.contains("'I'"));
+    assert_eq!(notes[0].to_string(), "5:1: warning: Ignored text 'stray text'");
+
+    // serde counts bytes, notes count characters
+    let (_,notes) = lsys_json_parse(r#"{"title": "ééé", "colors": 3, "start": "F"}"#);
+    assert_eq!((notes[0].line, notes[0].column), (1, 28));
+}
+
+/*----------------------------------------------------------------------
+The text format.  Every example, and an LSys with every field set,
+must come back the same after writing and reading it.
+*/
+
+#[test]
+fn test_text() {
+    use text::{lsys_text_parse, lsys_to_text};
+    let mut lsysv = lsys_json_parse(include_str!("lsys_examples.json")).0;
+    lsysv.push(LSys {
+        title: "Everything".to_string(),
+        refs: vec!["a:b".to_string(), "c".to_string()],
+        start: "A(1)B".to_string(),
+        angle: 22.5,
+        order: vec![0, 2, 5],
+        rules: HashMap::from([
+            ("A(x) : x > 0".to_string(), "A(x*R)".into()),
+            ("-".to_string(), "".into()),
+            ("#".to_string(), "!#(2)".into()),
+            ("# > B".to_string(), "!".into()),
+            ("B".to_string(), Successor::Stochastic(vec![(0.25, "B".to_string()), (1.0, "BB".to_string())])),
+        ]),
+        post_rules: HashMap::from([("B".to_string(), "F".into())]),
+        seed: 42,
+        ignore: "+-".to_string(),
+        consts: HashMap::from([("R".to_string(), 1.456), ("S".to_string(), -0.1)]),
+        tables: HashMap::from([
+            ("grow".to_string(), HashMap::from([("B".to_string(), "BB".into())])),
+            ("rest".to_string(), HashMap::new()),
+        ]),
+        schedule: vec![("grow".to_string(), 2), ("rest".to_string(), 1)],
+        colors: vec!["green".to_string(), "#803000".to_string()],
+        length_factor: 1.5,
+        width_factor: 0.5,
+        draws: "Apex".to_string(),
+        moves: "m".to_string(),
+        symbols: vec!["Apex".to_string()],
+    });
+    let text:Vec<String> = lsysv.iter().map(lsys_to_text).collect();
+    let (again,notes) = lsys_text_parse(&text.join("\n"));
+    assert_eq!(notes, vec!());
+    assert_eq!(again, lsysv);
+
+    // without a title, with comments, then more than one
+    let text = indoc! {"
+        # the Hilbert curve
+        axiom: X
+        angle: 90
+
+        X -> -YF+XFX+FY-
+        Y -> +XF-YFY-FX+
+        title: Two
+        #
+        axiom: F
+        F -> F+F
+        # -> #(2)
+    "};
+    let (lsysv,notes) = lsys_text_parse(text);
+    assert!(notes.is_empty());
+    assert_eq!(lsysv.len(), 2);
+    assert_eq!((lsysv[0].title.as_str(), lsysv[0].start.as_str()), ("", "X"));
+    assert_eq!(lsysv[0].rules["X"], "-YF+XFX+FY-".into());
+    assert_eq!(lsysv[1].title, "Two");
+    assert_eq!(lsysv[1].rules["#"], "#(2)".into());
+
+    // errors skip the rest of their LSys, not the next one
+    let text = indoc! {"
+        title: Bad angle
+        angle: ninety
+        F -> FF
+        title: Twice
+        axiom: F
+          axiom: G
+        title: Not a rule
+        axiom: F
+        F = FF
+        title: Does not check
+        axiom: F(
+        title: Fine
+        F -> F+F
+    "};
+    let (lsysv,notes) = lsys_text_parse(text);
+    assert_eq!(lsysv.len(), 1);
+    assert_eq!(lsysv[0].title, "Fine");
+    let places:Vec<(usize,usize)> = notes.iter().map(|n| (n.line, n.column)).collect();
+    assert_eq!(places, [(2,1), (6,3), (9,1), (10,1)]);
+    assert_eq!(notes[0].to_string(), "2:1: error: Bad number 'ninety' for 'angle'");
+    assert!(notes[1].text.contains("'axiom' given twice"));
+    // loading a file names it in the notes, which are returned rather than printed
+    let (named,notes) = text::lsys_from_text("bad.lsys", text);
+    assert_eq!(named, lsysv);
+    assert_eq!(notes[0].to_string(), "bad.lsys:2:1: error: Bad number 'ninety' for 'angle'");
+}
+
+/*----------------------------------------------------------------------
+Fractint entries derive the same strings as the examples they copy,
+with their own commands turned into actions.
+*/
+
+#[test]
+fn test_fractint() {
+    use fractint::lsys_fractint_parse;
+    let src = indoc! {r#"
+        ; comments, and names in any case
+        Hilbert {       ; Hilbert curve
+          Angle 4
+          Axiom X
+          X=-YF+XFX+FY-
+          Y=+XF-YFY-FX+
+        }
+        koch { angle 6
+          axiom +f--f--f
+          f=f+f--f+f }
+        Commands {
+          Angle 5
+          Axiom DG|M\30/12.5@IQ4C3
+          D=.D
+        }
+        Colors {
+          Angle 4
+          Axiom F<2F>1F
+        }
+        Swap {
+          Angle 4
+          Axiom F!F
+        }
+        NoAngle {
+          Axiom F
+        }
+        Open {
+          Angle 4
+    "#};
+    let (lsysv,notes) = lsys_fractint_parse(src);
+    let titles:Vec<&str> = lsysv.iter().map(|l| l.title.as_str()).collect();
+    assert_eq!(titles, ["Hilbert", "koch", "Commands", "Colors"]);
+
+    let examples = lsys_json_parse(include_str!("lsys_examples.json")).0;
+    for (i,title) in [(0,"Hilbert Curve"), (1,"Koch's Snowflake")] {
+        let example = examples.iter().find(|l| l.title == title).unwrap();
+        assert_eq!(lsysv[i].angle, example.angle);
+        for order in 0..4 {
+            assert_eq!(
+                lsys_apply_rules(&lsysv[i], order).unwrap(),
+                lsys_apply_rules(example, order).unwrap());
+        }
+    }
+
+    // "|" for five parts turns by two of them, "." is renamed
+    let commands = &lsysv[2];
+    assert_eq!(commands.angle, 72.0);
+    assert_eq!(commands.start, "Dg+(144)M+(30)-(12.5)>(0.5)'(3)");
+    assert_eq!(commands.rules["D"], "aD".into());
+    assert_eq!((commands.draws.as_str(), commands.moves.as_str()), ("D", "M"));
+    assert_eq!(lsysv[3].start, "FFF");
+
+    let places:Vec<(usize,usize,bool)> = notes.iter()
+        .map(|n| (n.line, n.column, n.error)).collect();
+    assert_eq!(places, [
+        (18, 3, false),     // "<" left out
+        (18, 3, false),     // ">" left out
+        (22, 3, true),      // "!"
+        (24, 1, true),      // no Angle
+        (27, 1, true),      // no "}"
+    ]);
+
+    // entries sharing lines, and text after the last
+    let (lsysv,notes) = lsys_fractint_parse("One {\nAngle 4\nAxiom F } Two { Angle 6\nAxiom FF } stray\n");
+    let titles:Vec<&str> = lsysv.iter().map(|l| l.title.as_str()).collect();
+    assert_eq!(titles, ["One", "Two"]);
+    assert_eq!((lsysv[1].angle, lsysv[1].start.as_str()), (60.0, "FF"));
+    assert_eq!(notes.len(), 1);
+    assert_eq!(notes[0].to_string(), "4:12: warning: Ignored text 'stray'");
+}
+
+/*----------------------------------------------------------------------
+PostScript documents and eps figures.  The output is checked for its
+structure, and the eps bounding box must hold every line drawn.
+*/
+
+// a writer whose bytes can be looked at once the document is closed
+#[derive(Clone, Default)]
+struct SharedBuf(std::rc::Rc<std::cell::RefCell<Vec<u8>>>);
+
+impl Write for SharedBuf {
+    fn write(&mut self, buf:&[u8]) -> std::io::Result<usize> {
+        self.0.borrow_mut().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl SharedBuf {
+    fn text(&self) -> String {
+        String::from_utf8(self.0.borrow().clone()).unwrap()
+    }
+}
+
+#[test]
+fn test_ps() {
+    assert_eq!(ps::ps_string("a(b)\\ é"), "(a\\(b\\)\\\\ ?)");
+    assert_eq!(color::color_rgb("DarkGreen"), Some([0x00,0x64,0x00]));
+    assert_eq!(color::color_rgb("#f80"), Some([0xff,0x88,0x00]));
+    assert_eq!(color::color_rgb("#12345"), None);
+
+    // pages, with one which can not be drawn left out
+    let examples = lsys_json_parse(include_str!("lsys_examples.json")).0;
+    let bad = LSys { title: "Bad".to_string(), start: "F]".to_string(), ..examples[0].clone() };
+    let lsysv = vec![examples[0].clone(), bad, examples[1].clone()];
+    let out = SharedBuf::default();
+    let ds = &mut doc_new_ps();
+    doc(ds, DocAct::DocOpenWriterTitle(Box::new(out.clone()), "test.ps", "Test")).unwrap();
+    let skipped = lsys_draw_pages(&lsysv, ds, lsys_draw_page).unwrap();
+    assert!(matches!(&skipped[..], [(title, LSysError::Unbalanced { .. })] if title == "Bad"));
+    let ps = out.text();
+    assert!(ps.starts_with("%!PS-Adobe-3.0\n%%Title: Test\n"));
+    assert!(ps.contains("%%Pages: (atend)\n") && ps.contains("%%EndProlog\n"));
+    assert!(ps.contains("\n%%Page: 1 1\n") && ps.contains("\n%%Page: 2 2\n"));
+    assert!(!ps.contains("%%Page: 3") && !ps.contains("% Bad"));
+    assert!(ps.ends_with("%%Trailer\n%%Pages: 2\n%%EOF\n"));
+    let refs = examples[0].refs.len() + examples[1].refs.len();
+    assert_eq!(ps.matches("/ANN pdfmark").count(), refs);
+    assert_eq!(ps.matches("gsave").count(), ps.matches("grestore").count());
+
+    // an order too high leaves its box empty, and the page is kept
+    let high = LSys { order: vec![1, 200], ..examples[1].clone() };
+    let ds = &mut doc_new_ps();
+    doc(ds, DocAct::DocOpenWriterTitle(Box::new(SharedBuf::default()), "test.ps", "Test")).unwrap();
+    let skipped = lsys_draw_pages(&[high], ds, lsys_draw_page).unwrap();
+    assert!(matches!(&skipped[..], [(_, LSysError::OrderTooHigh { order: 200 })]));
+
+    // a figure, cropped to what it draws
+    let out = SharedBuf::default();
+    let ds = &mut doc_new_eps();
+    let koch = &examples[1];
+    doc(ds, DocAct::DocOpenWriterTitle(Box::new(out.clone()), "test.eps", &koch.title)).unwrap();
+    doc(ds, DocAct::PageStartComment(&koch.title)).unwrap();
+    lsys_draw_figure(koch, ds).unwrap();
+    doc(ds, DocAct::PageEnd).unwrap();
+    assert!(matches!(doc(ds, DocAct::PageStartComment("")), Err(LSysError::Doc(_))));
+    doc(ds, DocAct::DocClose).unwrap();
+    let eps = out.text();
+    assert!(eps.starts_with("%!PS-Adobe-3.0 EPSF-3.0\n"));
+    assert!(!eps.contains("findfont") && !eps.contains("DocumentNeededResources"));
+    assert!(eps.ends_with("%%Trailer\n%%EOF\n"));
+
+    let line = eps.lines().find(|l| l.starts_with("%%BoundingBox:")).unwrap();
+    let bb:Vec<f64> = line.split_whitespace().skip(1).map(|v| v.parse().unwrap()).collect();
+    assert!(0.0 <= bb[0] && bb[0] < bb[2] && bb[2] <= 612.0);
+    assert!(0.0 <= bb[1] && bb[1] < bb[3] && bb[3] <= 792.0);
+    let mut seen = (f64::MAX, f64::MAX, f64::MIN, f64::MIN);
+    let words:Vec<&str> = eps.split_whitespace().collect();
+    for w in words.windows(3).filter(|w| w[2] == "l") {
+        // pixels, y down, to points, y up
+        let x = w[0].parse::<f64>().unwrap() * 0.75;
+        let y = 792.0 - w[1].parse::<f64>().unwrap() * 0.75;
+        seen = (seen.0.min(x), seen.1.min(y), seen.2.max(x), seen.3.max(y));
+    }
+    assert!(bb[0] <= seen.0 && bb[1] <= seen.1 && seen.2 <= bb[2] && seen.3 <= bb[3]);
+    assert!(seen.0 - bb[0] < 2.0 && seen.1 - bb[1] < 2.0);
+    assert!(bb[2] - seen.2 < 2.0 && bb[3] - seen.3 < 2.0);
+}
+
+/*----------------------------------------------------------------------
+PDF documents.  The cross reference table must point at each object,
+and each page must have its links.
+*/
+
+#[test]
+fn test_pdf() {
+    assert!((pdf::pdf_text_width(2, "abc") - 1.8).abs() < 1e-9);
+    assert!((pdf::pdf_text_width(1, "Il é") - 1.334).abs() < 1e-9);
+
+    let examples = lsys_json_parse(include_str!("lsys_examples.json")).0;
+    let bad = LSys { title: "Bad".to_string(), start: "F]".to_string(), ..examples[0].clone() };
+    let lsysv = vec![examples[0].clone(), bad, examples[1].clone()];
+    let out = SharedBuf::default();
+    let ds = &mut doc_new_pdf();
+    doc(ds, DocAct::DocOpenWriterTitle(Box::new(out.clone()), "test.pdf", "Test (1)")).unwrap();
+    lsys_draw_pages(&lsysv, ds, lsys_draw_page).unwrap();
+    // the binary comment and compressed streams, made text so that
+    // offsets stay as they are
+    let bytes = out.0.borrow().clone();
+    assert_eq!(&bytes[9..15], b"%\xe2\xe3\xcf\xd3\n");
+    let pdf:String = bytes.iter().map(|&b| if b.is_ascii() { b as char } else { '?' }).collect();
+    assert!(pdf.starts_with("%PDF-1.4\n") && pdf.ends_with("%%EOF\n"));
+    assert!(pdf.contains("/Title (Test \\(1\\))") && !pdf.contains("% Bad"));
+    assert!(pdf.contains("/Count 2 >>") && pdf.contains("/BaseFont /Courier "));
+
+    // the table, then each entry, where its object starts
+    let (_,tail) = pdf.rsplit_once("startxref\n").unwrap();
+    let xref:usize = tail.lines().next().unwrap().parse().unwrap();
+    let mut lines = pdf[xref..].lines();
+    assert_eq!(lines.next(), Some("xref"));
+    let size:usize = lines.next().unwrap()[2..].parse().unwrap();
+    assert_eq!(lines.next(), Some("0000000000 65535 f "));
+    for n in 1..size {
+        let entry = lines.next().unwrap();
+        let offset:usize = entry[..10].parse().unwrap();
+        assert!(pdf[offset..].starts_with(&format!("{n} 0 obj\n")));
+    }
+    assert!(pdf.contains(&format!("/Size {size} /Root 1 0 R")));
+
+    // stream lengths, each compressed, and the links
+    let mut streams = 0;
+    for (at,_) in pdf.match_indices("/Length ") {
+        let rest = &pdf[at + 8..];
+        let length:usize = rest[..rest.find(' ').unwrap()].parse().unwrap();
+        assert!(rest[rest.find(' ').unwrap()..].starts_with(" /Filter /FlateDecode >>"));
+        let start = rest.find("stream\n").unwrap() + 7;
+        assert_eq!(&bytes[at + 8 + start..at + 8 + start + 2], &[0x78, 0x01]);
+        assert!(rest[start + length..].starts_with("endstream"));
+        streams += 1;
+    }
+    assert_eq!(streams, 2);
+    let refs = examples[0].refs.len() + examples[1].refs.len();
+    assert_eq!(pdf.matches("/Subtype /Link").count(), refs);
+    assert!(pdf.contains(&format!("/URI {}", ps::ps_string(&examples[0].refs[0]))));
+}
+
+/*----------------------------------------------------------------------
+Zlib.  The checksum against its published check value, and a run of
+zeros, which should compress to almost nothing.
+*/
+
+#[test]
+fn test_zlib() {
+    assert_eq!(zlib::zlib_adler32(b"Wikipedia"), 0x11e60398);
+    let z = zlib::zlib_compress(&[0u8; 100_000]);
+    assert!(z.starts_with(&[0x78, 0x01]) && z.len() < 1000);
+    assert!(z.ends_with(&zlib::zlib_adler32(&[0u8; 100_000]).to_be_bytes()));
+}
+
+/*----------------------------------------------------------------------
+PNG images.  The chunk checksum against its published check value,
+lines on a raster, and the chunks of a cropped figure.
+*/
+
+#[test]
+fn test_png() {
+    assert_eq!(png::png_crc32(b"123456789"), 0xcbf43926);
+
+    // a line 2 pixels wide, across y = 10.5, smoothed or not
+    for antialias in [true, false] {
+        let opts = png::PngOptions { antialias, ..png::png_options_new() };
+        let mut r = png::raster_new(&opts);
+        png::raster_line(&mut r, (10.0, 10.5), (50.0, 10.5), 2.0, [0, 0, 0]);
+        let png = png::png_encode(&r, &(0.0, 0.0, 60.0, 20.0));
+        assert_eq!(&png[16..24], &[0, 0, 0, 60, 0, 0, 0, 20]);
+        let at = |i:usize, j:usize| r.pixels[j * r.width + i];
+        assert_eq!(at(30, 10), [0, 0, 0, 255]);
+        assert_eq!(at(30, 14), [255, 255, 255, 255]);
+        let edge = at(30, 11)[0];
+        assert!(if antialias { edge > 0 && edge < 255 } else { edge == 0 });
+        assert_eq!(at(30, 12), [255, 255, 255, 255]);
+    }
+
+    // half covered over nothing keeps its color, then blends by coverage
+    let mut r = png::raster_new(&png::PngOptions { background: None, ..png::png_options_new() });
+    png::raster_rect(&mut r, 0.0, 0.0, 0.5, 1.0, [200, 100, 0]);
+    assert_eq!(r.pixels[0], [200, 100, 0, 128]);
+    png::raster_rect(&mut r, 0.0, 0.0, 0.5, 1.0, [0, 0, 200]);
+    assert_eq!(r.pixels[0], [67, 33, 133, 192]);
+
+    // a figure, cropped and transparent
+    let lsys = &lsys_json_parse(include_str!("lsys_examples.json")).0[1];
+    let opts = png::PngOptions { background: None, ..png::png_options_new() };
+    let out = SharedBuf::default();
+    let ds = &mut doc_new_png(opts);
+    doc(ds, DocAct::DocOpenWriterTitle(Box::new(out.clone()), "test.png", &lsys.title)).unwrap();
+    doc(ds, DocAct::PageStartComment(&lsys.title)).unwrap();
+    lsys_draw_figure(lsys, ds).unwrap();
+    doc(ds, DocAct::PageEnd).unwrap();
+    assert!(matches!(doc(ds, DocAct::PageStartComment("")), Err(LSysError::Doc(_))));
+    doc(ds, DocAct::DocClose).unwrap();
+    let png = out.0.borrow().clone();
+    assert!(png.starts_with(b"\x89PNG\r\n\x1a\n"));
+    let mut at = 8;
+    let mut kinds = vec!();
+    while at < png.len() {
+        let n = u32::from_be_bytes(png[at..at+4].try_into().unwrap()) as usize;
+        let crc = u32::from_be_bytes(png[at+8+n..at+12+n].try_into().unwrap());
+        assert_eq!(png::png_crc32(&png[at+4..at+8+n]), crc);
+        kinds.push(String::from_utf8_lossy(&png[at+4..at+8]).to_string());
+        at += 12 + n;
+    }
+    assert_eq!(kinds, ["IHDR", "pHYs", "IDAT", "IEND"]);
+    let width = u32::from_be_bytes(png[16..20].try_into().unwrap());
+    let height = u32::from_be_bytes(png[20..24].try_into().unwrap());
+    assert!(width < 816 && height < 1056 && width > 100 && height > 100);
+}
+
+/*----------------------------------------------------------------------
+Standalone svg figures, and a directory of them from the command line
+*/
+
+#[test]
+fn test_svg_figure() {
+    let mut koch = lsys_json_parse(include_str!("lsys_examples.json")).0[1].clone();
+    koch.title = "Koch & <Snow>".to_string();
+    let svg = lsys_svg_figure(&koch, 2).unwrap();
+    assert!(svg.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"no\"?>\n<svg\n"));
+    assert!(svg.contains("xmlns=\"http://www.w3.org/2000/svg\""));
+    assert!(svg.contains("<title>Koch &amp; &lt;Snow&gt;</title>"));
+    assert!(svg.contains("Order 2 of a Lindenmayer system\n") && svg.contains("F : F+F--F+F\n"));
+    assert!(svg.ends_with("</svg>\n") && !svg.contains("<text"));
+
+    // the longer side, and the width and height, are those of the view box
+    let attr = |name:&str| {
+        let at = svg.find(&format!("{name}=\"")).unwrap() + name.len() + 2;
+        svg[at..at + svg[at..].find('"').unwrap()].to_string()
+    };
+    let vb:Vec<f64> = attr("viewBox").split(' ').map(|v| v.parse().unwrap()).collect();
+    assert_eq!(format!("{:.2}", vb[2]), attr("width"));
+    assert_eq!(format!("{:.2}", vb[3]), attr("height"));
+    assert!((vb[2].max(vb[3]) - (480.0 + 1.5)).abs() < 0.01);
+
+    use cli::{cli_parse, cli_lsys, cli_run};
+    let args = |s:&str| s.split_whitespace().map(String::from).collect::<Vec<String>>();
+    assert!(matches!(cli_parse(&args("-d figs -o x.svg")), Err(LSysError::Usage(_))));
+    assert!(matches!(cli_parse(&args("-d figs -f png")), Err(LSysError::Usage(_))));
+    let dir = std::env::temp_dir().join(format!("rust_svg_figures_{}", std::process::id()));
+    let dir = dir.to_string_lossy().to_string();
+    let opts = cli_parse(&args(&format!("-t koch -d {dir}"))).unwrap();
+    cli_run(&opts, &cli_lsys(&opts).unwrap().0).unwrap();
+    let mut names:Vec<String> = std::fs::read_dir(&dir).unwrap()
+        .map(|e| e.unwrap().file_name().to_string_lossy().to_string()).collect();
+    names.sort();
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(names, ["koch_s_snowflake_0.svg", "koch_s_snowflake_1.svg",
+        "koch_s_snowflake_2.svg", "koch_s_snowflake_4.svg"]);
+}
+
+#[test]
+fn test_plot() {
+    use plot::*;
+    let plant = &lsys_json_parse(include_str!("lsys_examples.json")).0[10];
+    assert_eq!(plant.title, "Plant 1");
+    let mut opts = plot_options_new();
+    (opts.width, opts.height, opts.reorder) = (100.0, 50.0, false);
+    let drawn = lsys_plot_paths(plant, 4, &opts).unwrap();
+    opts.reorder = true;
+    let ordered = lsys_plot_paths(plant, 4, &opts).unwrap();
+
+    // the same lines, fitted to the area, with less travel between them
+    let length = |paths:&[PlotPath]| paths.iter()
+        .flat_map(|p| p.points.windows(2))
+        .map(|w| (w[0].0 - w[1].0).hypot(w[0].1 - w[1].1))
+        .sum::<f64>();
+    assert!((length(&drawn) - length(&ordered)).abs() < 1e-6);
+    let points = || ordered.iter().flat_map(|p| p.points.iter());
+    assert!(points().all(|p| (-1e-9..=100.0 + 1e-9).contains(&p.0) && (-1e-9..=50.0 + 1e-9).contains(&p.1)));
+    assert!((points().map(|p| p.1).fold(0.0, f64::max) - 50.0).abs() < 1e-9);
+    assert!(plot_travel(&ordered) < plot_travel(&drawn) / 2.0);
+
+    // and fewer lifts of the pen
+    let gcode = gcode_from_paths("Plant 1", &ordered, &opts);
+    let lifts = |g:&str| g.lines().filter(|l| *l == "G0 Z5").count();
+    assert!(lifts(&gcode) < lifts(&gcode_from_paths("Plant 1", &drawn, &opts)));
+    assert!(gcode.starts_with("; Plant 1\n") && gcode.contains("G21 ; mm\nG90 ; absolute\nG0 Z5\nG0 X"));
+    assert!(gcode.ends_with("G0 Z5\nG0 X0 Y0 F3000\nM2\n"));
+    let lines:usize = ordered.iter().map(|p| p.points.len() - 1).sum();
+    assert_eq!(gcode.lines().filter(|l| l.starts_with("G1 ")).count(), lines);
+    assert_eq!(gcode.matches(" F1000\n").count(), lifts(&gcode) - 1);
+
+    let hpgl = hpgl_from_paths(&ordered, &opts);
+    assert!(hpgl.starts_with("IN;\nVS1.7;\nSP1;\nPU") && hpgl.ends_with("PU0,0;\nSP0;\n"));
+    assert_eq!(hpgl.matches("PU").count(), lifts(&gcode));
+    assert!(hpgl.contains(",2000;") || hpgl.contains(",2000,"));
 }
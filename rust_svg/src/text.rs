@@ -0,0 +1,317 @@
+use super::*;
+
+/*----------------------------------------------------------------------
+Text format
+
+A compact way to write an LSys by hand, without the quotes and braces
+of json.  Each line is a field or a rule:
+
+    title: Dragon Curve
+    ref: https://en.wikipedia.org/wiki/Dragon_curve
+    axiom: FX
+    angle: 90
+    order: 1, 4, 8, 12
+    X -> X+YF+
+    Y -> -FX-Y
+
+A "title" line starts a new LSys, so a file may hold any number of
+them.  Lines before the first title belong to an LSys without one.
+Blank lines are ignored, and so are comments, which start with "#"
+then a space or the end of the line.  Since "#" is also an action, a
+comment can not follow anything else on a line, and a line such as
+"# -> !" is a rule for "#": one where "->", "<", ">" or ":" follows
+the "#" and its spaces.
+
+The other fields are:
+
+    seed: 7                     random seed
+    ignore: +-F                 symbols skipped by context matching
+    symbols: Apex Internode     names of more than one character
+    draws: AB                   extra symbols which draw like "F"
+    moves: ab                   extra symbols which move like "f"
+    colors: green brown         color map
+    length_factor: 1.5          for ">" and "<"
+    width_factor: 0.5           for "!"
+    const: R = 1.456            one named value for rule expressions
+    schedule: grow 4, flower 1  tables, and for how many steps
+
+"ref" and "const" may be given any number of times, the rest once.
+
+A rule is the left hand side, as in json, then "->" and the successor.
+The first "->" splits them, so a left hand side ending in "-" is fine,
+but one containing "->" needs a space between the two.  Each
+alternative of a stochastic rule is a line of its own, with its weight
+in parentheses after the arrow:
+
+    F ->(0.33) F[+F]F[-F]F
+    F ->(0.67) F[+F]F
+
+Post rules start with "post:", and the rules of a table with
+"table NAME:", which alone declares an empty table:
+
+    post: X -> F
+    table grow: A -> F[+A]F[-A]A
+
+lsys_to_text writes every field that differs from its default, with
+rules sorted, so that reading the text gives back the same LSys.
+
+A line which can not be read is reported with its line and column,
+and the LSys it belongs to is skipped up to the next title.
+*/
+
+// fields given at most once
+static TEXT_FIELDS:[&str;12] = [
+    "axiom", "angle", "order", "seed", "ignore", "symbols",
+    "draws", "moves", "colors", "length_factor", "width_factor", "schedule",
+];
+
+struct TextLSys {
+    lsys:   LSys,
+    line:   usize,              // where it starts
+    given:  Vec<String>,        // fields seen so far
+    failed: bool,               // skip the rest
+}
+
+fn text_number<T:std::str::FromStr>(name:&str, value:&str) -> Result<T,String> {
+    value.parse().map_err(|_| format!("Bad number '{value}' for '{name}'"))
+}
+
+// "LHS -> successor" or "LHS ->(weight) successor", added to rules
+fn text_rule(rules:&mut Rules, src:&str) -> Result<(),String> {
+    let (lhs,rhs) = src.split_once("->")
+        .ok_or_else(|| format!("Expected a field or a rule, not '{src}'"))?;
+    let lhs = lhs.trim();
+    if lhs.is_empty() {
+        return Err(format!("No left hand side in rule '{src}'"));
+    }
+    let rhs = rhs.trim();
+    let weight = match rhs.strip_prefix('(') {
+        None => None,
+        Some(rest) => {
+            let (w,succ) = rest.split_once(')')
+                .ok_or_else(|| format!("Unbalanced '(' in weight of rule '{src}'"))?;
+            Some((text_number::<f64>("weight", w.trim())?, succ.trim().to_string()))
+        }
+    };
+    match (rules.get_mut(lhs), weight) {
+        (None, None) => {
+            rules.insert(lhs.to_string(), rhs.into());
+        }
+        (None, Some(alt)) => {
+            rules.insert(lhs.to_string(), Successor::Stochastic(vec![alt]));
+        }
+        (Some(Successor::Stochastic(alts)), Some(alt)) => alts.push(alt),
+        (Some(_), _) => return Err(format!("Rule for '{lhs}' given twice")),
+    }
+    Ok(())
+}
+
+// one line, which is not a title
+fn text_line(t:&mut TextLSys, line:&str) -> Result<(),String> {
+    let lsys = &mut t.lsys;
+    let (name,value) = match line.split_once(':') {
+        Some((n,v)) if TEXT_FIELDS.contains(&n.trim())
+            || ["ref", "const", "post"].contains(&n.trim())
+            || n.starts_with("table ") => (n.trim(), v.trim()),
+        _ => return text_rule(&mut lsys.rules, line),
+    };
+    if TEXT_FIELDS.contains(&name) {
+        if t.given.iter().any(|g| g == name) {
+            return Err(format!("Field '{name}' given twice"));
+        }
+        t.given.push(name.to_string());
+    }
+    let words = || value.split_whitespace().map(String::from).collect();
+    match name {
+        "ref"    => lsys.refs.push(value.to_string()),
+        "axiom"  => lsys.start = value.to_string(),
+        "angle"  => lsys.angle = text_number(name, value)?,
+        "seed"   => lsys.seed = text_number(name, value)?,
+        "ignore" => lsys.ignore = value.to_string(),
+        "draws"  => lsys.draws = value.to_string(),
+        "moves"  => lsys.moves = value.to_string(),
+        "symbols" => lsys.symbols = words(),
+        "colors" => lsys.colors = words(),
+        "length_factor" => lsys.length_factor = text_number(name, value)?,
+        "width_factor" => lsys.width_factor = text_number(name, value)?,
+        "order" => {
+            lsys.order = value.split([',', ' ']).filter(|s| !s.is_empty())
+                .map(|s| text_number(name, s)).collect::<Result<_,_>>()?;
+        }
+        "const" => {
+            let (c,v) = value.split_once('=')
+                .ok_or_else(|| format!("Expected 'const: NAME = VALUE', not '{line}'"))?;
+            lsys.consts.insert(c.trim().to_string(), text_number(name, v.trim())?);
+        }
+        "schedule" => {
+            for step in value.split(',') {
+                let (table,count) = step.trim().rsplit_once(' ')
+                    .ok_or_else(|| format!("Expected 'TABLE COUNT' in schedule, not '{}'", step.trim()))?;
+                lsys.schedule.push((table.trim().to_string(), text_number(name, count)?));
+            }
+        }
+        "post" => text_rule(&mut lsys.post_rules, value)?,
+        _ => {
+            // "table NAME"
+            let rules = lsys.tables.entry(name["table ".len()..].trim().to_string()).or_default();
+            if !value.is_empty() {
+                text_rule(rules, value)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+// check a finished LSys, and keep it or note why not
+fn text_finish(t:TextLSys, lsysv:&mut Vec<LSys>, notes:&mut Vec<LoadNote>) {
+    if t.failed {
+        return;
+    }
+    match lsys_check(&t.lsys) {
+        Ok(()) => lsysv.push(t.lsys),
+        Err(why) => notes.push(LoadNote {
+            line: t.line,
+            column: 1,
+            error: true,
+            text: format!("'{}': {why}", t.lsys.title),
+            source: String::new(),
+        }),
+    }
+}
+
+/// The LSys values of text, and notes on what was skipped
+pub fn lsys_text_parse(text:&str) -> (Vec<LSys>,Vec<LoadNote>) {
+    let mut lsysv = vec!();
+    let mut notes = vec!();
+    let mut cur:Option<TextLSys> = None;
+    for (i,raw) in text.lines().enumerate() {
+        let line = raw.trim();
+        if line.is_empty() || text_comment(line) {
+            continue;
+        }
+        let title = line.strip_prefix("title:");
+        if title.is_some() || cur.is_none() {
+            if let Some(t) = cur.take() {
+                text_finish(t, &mut lsysv, &mut notes);
+            }
+            let lsys = LSys { title: title.unwrap_or("").trim().to_string(), ..Default::default() };
+            cur = Some(TextLSys { lsys, line: i+1, given: vec!(), failed: false });
+            if title.is_some() {
+                continue;
+            }
+        }
+        let Some(t) = cur.as_mut().filter(|t| !t.failed) else {
+            continue;
+        };
+        if let Err(text) = text_line(t, line) {
+            let column = raw[..raw.len() - raw.trim_start().len()].chars().count() + 1;
+            notes.push(LoadNote { line: i+1, column, error: true, text, source: String::new() });
+            t.failed = true;
+        }
+    }
+    if let Some(t) = cur {
+        text_finish(t, &mut lsysv, &mut notes);
+    }
+    (lsysv, notes)
+}
+
+// "#" then a space or nothing, but not a rule for "#", as in "# -> !"
+fn text_comment(line:&str) -> bool {
+    let Some(rest) = line.strip_prefix('#') else {
+        return false;
+    };
+    let after = rest.trim_start();
+    (rest.is_empty() || rest.len() > after.len())
+        && !["->", "<", ">", ":"].iter().any(|s| after.starts_with(s))
+}
+
+/// Load lsys from text, with problems noted against the name of the source
+pub fn lsys_from_text(name:&str, text:&str) -> (Vec<LSys>,Vec<LoadNote>) {
+    load_named(name, lsys_text_parse(text))
+}
+
+// rules sorted by left hand side, each line after prefix
+fn text_rules(out:&mut String, prefix:&str, rules:&Rules) {
+    let mut lhsv:Vec<&String> = rules.keys().collect();
+    lhsv.sort();
+    for lhs in lhsv {
+        match &rules[lhs] {
+            Successor::Basic(s) => {
+                out.push_str(format!("{prefix}{lhs} -> {s}").trim_end());
+                out.push('\n');
+            }
+            Successor::Stochastic(alts) => {
+                for (w,s) in alts {
+                    out.push_str(format!("{prefix}{lhs} ->({w}) {s}").trim_end());
+                    out.push('\n');
+                }
+            }
+        }
+    }
+}
+
+/// An LSys as text, which lsys_text_parse reads back
+pub fn lsys_to_text(lsys:&LSys) -> String {
+    let mut out = String::new();
+    let mut field = |name:&str, value:String| {
+        out.push_str(format!("{name}: {value}").trim_end());
+        out.push('\n');
+    };
+    field("title", lsys.title.clone());
+    for r in &lsys.refs {
+        field("ref", r.clone());
+    }
+    field("axiom", lsys.start.clone());
+    field("angle", lsys.angle.to_string());
+    if !lsys.order.is_empty() {
+        let orders:Vec<String> = lsys.order.iter().map(|o| o.to_string()).collect();
+        field("order", orders.join(", "));
+    }
+    if lsys.seed != 0 {
+        field("seed", lsys.seed.to_string());
+    }
+    for (name,value) in [
+        ("ignore", &lsys.ignore),
+        ("draws",  &lsys.draws),
+        ("moves",  &lsys.moves),
+    ] {
+        if !value.is_empty() {
+            field(name, value.clone());
+        }
+    }
+    if !lsys.symbols.is_empty() {
+        field("symbols", lsys.symbols.join(" "));
+    }
+    if !lsys.colors.is_empty() {
+        field("colors", lsys.colors.join(" "));
+    }
+    if lsys.length_factor != 0.0 {
+        field("length_factor", lsys.length_factor.to_string());
+    }
+    if lsys.width_factor != 0.0 {
+        field("width_factor", lsys.width_factor.to_string());
+    }
+    let mut consts:Vec<(&String,&f64)> = lsys.consts.iter().collect();
+    consts.sort_by(|a,b| a.0.cmp(b.0));
+    for (c,v) in consts {
+        field("const", format!("{c} = {v}"));
+    }
+
+    text_rules(&mut out, "", &lsys.rules);
+    text_rules(&mut out, "post: ", &lsys.post_rules);
+    let mut names:Vec<&String> = lsys.tables.keys().collect();
+    names.sort();
+    for name in names {
+        let prefix = format!("table {name}: ");
+        if lsys.tables[name].is_empty() {
+            out.push_str(prefix.trim_end());
+            out.push('\n');
+        }
+        text_rules(&mut out, &prefix, &lsys.tables[name]);
+    }
+    if !lsys.schedule.is_empty() {
+        let steps:Vec<String> = lsys.schedule.iter().map(|(t,n)| format!("{t} {n}")).collect();
+        out.push_str(&format!("schedule: {}\n", steps.join(", ")));
+    }
+    out
+}
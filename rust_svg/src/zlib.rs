@@ -0,0 +1,160 @@
+/*----------------------------------------------------------------------
+Zlib compression
+
+Compression in the zlib format, which pdf reads for page contents and
+png for image data.  Deflate is written with fixed Huffman codes,
+which is simple, and for drawings, whose numbers and pixels repeat,
+small.
+
+    https://www.rfc-editor.org/rfc/rfc1950     zlib
+    https://www.rfc-editor.org/rfc/rfc1951     deflate
+*/
+
+// checksum of the data before compression, which ends the stream
+pub(crate) fn zlib_adler32(bytes:&[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for chunk in bytes.chunks(5552) {
+        for &byte in chunk {
+            a += byte as u32;
+            b += a;
+        }
+        a %= 65521;
+        b %= 65521;
+    }
+    (b << 16) | a
+}
+
+// bits, packed from the least significant up, as deflate wants
+struct Bits {
+    out: Vec<u8>,
+    acc: u32,
+    n:   u32,
+}
+
+fn bits_put(b:&mut Bits, value:u32, count:u32) {
+    b.acc |= value << b.n;
+    b.n += count;
+    while b.n >= 8 {
+        b.out.push(b.acc as u8);
+        b.acc >>= 8;
+        b.n -= 8;
+    }
+}
+
+// a Huffman code, which goes most significant bit first
+fn bits_code(b:&mut Bits, code:u32, count:u32) {
+    let reversed = (0..count).fold(0, |r,i| r | ((code >> i) & 1) << (count - 1 - i));
+    bits_put(b, reversed, count);
+}
+
+// the fixed code for a literal, a length or the end of the block
+fn deflate_symbol(b:&mut Bits, v:u32) {
+    match v {
+        0..=143   => bits_code(b, 0x30 + v, 8),
+        144..=255 => bits_code(b, 0x190 + v - 144, 9),
+        256..=279 => bits_code(b, v - 256, 7),
+        _         => bits_code(b, 0xc0 + v - 280, 8),
+    }
+}
+
+static DEFLATE_LENGTHS:[(u32,u32);29] = [      // base, extra bits
+    (3,0), (4,0), (5,0), (6,0), (7,0), (8,0), (9,0), (10,0),
+    (11,1), (13,1), (15,1), (17,1), (19,2), (23,2), (27,2), (31,2),
+    (35,3), (43,3), (51,3), (59,3), (67,4), (83,4), (99,4), (115,4),
+    (131,5), (163,5), (195,5), (227,5), (258,0),
+];
+
+static DEFLATE_DISTANCES:[(u32,u32);30] = [    // base, extra bits
+    (1,0), (2,0), (3,0), (4,0), (5,1), (7,1), (9,2), (13,2),
+    (17,3), (25,3), (33,4), (49,4), (65,5), (97,5), (129,6), (193,6),
+    (257,7), (385,7), (513,8), (769,8), (1025,9), (1537,9), (2049,10), (3073,10),
+    (4097,11), (6145,11), (8193,12), (12289,12), (16385,13), (24577,13),
+];
+
+fn deflate_match(b:&mut Bits, length:u32, distance:u32) {
+    let k = DEFLATE_LENGTHS.iter().rposition(|(base,_)| *base <= length).unwrap_or(0);
+    let (base,extra) = DEFLATE_LENGTHS[k];
+    deflate_symbol(b, 257 + k as u32);
+    bits_put(b, length - base, extra);
+    let k = DEFLATE_DISTANCES.iter().rposition(|(base,_)| *base <= distance).unwrap_or(0);
+    let (base,extra) = DEFLATE_DISTANCES[k];
+    bits_code(b, k as u32, 5);
+    bits_put(b, distance - base, extra);
+}
+
+static DEFLATE_WINDOW:usize = 32768;
+static DEFLATE_CHAIN:usize  = 64;       // earlier places tried for a match
+static DEFLATE_NONE:usize   = usize::MAX;
+
+// the first three bytes at i, hashed
+fn deflate_hash(data:&[u8], i:usize) -> usize {
+    ((data[i] as usize) << 10 ^ (data[i+1] as usize) << 5 ^ data[i+2] as usize) & 0x7fff
+}
+
+// note place i, the latest with its hash
+fn deflate_insert(data:&[u8], head:&mut [usize], prev:&mut [usize], i:usize) {
+    if i + 3 <= data.len() {
+        let h = deflate_hash(data, i);
+        prev[i % DEFLATE_WINDOW] = head[h];
+        head[h] = i;
+    }
+}
+
+/*
+Data compressed in the zlib format, as one block of fixed Huffman
+codes.  Each place is matched with the longest of the earlier places
+starting with the same three bytes, as far as DEFLATE_CHAIN of them.
+*/
+pub(crate) fn zlib_compress(data:&[u8]) -> Vec<u8> {
+    let mut b = Bits { out: vec![0x78, 0x01], acc: 0, n: 0 };
+    bits_put(&mut b, 1, 1);     // last block
+    bits_put(&mut b, 1, 2);     // fixed codes
+
+    let mut head = vec![DEFLATE_NONE; 1 << 15];
+    let mut prev = vec![DEFLATE_NONE; DEFLATE_WINDOW];
+
+    let mut i = 0;
+    while i < data.len() {
+        let (mut best, mut distance) = (0, 0);
+        if i + 3 <= data.len() {
+            let most = (data.len() - i).min(258);
+            let mut at = head[deflate_hash(data, i)];
+            for _ in 0..DEFLATE_CHAIN {
+                if at == DEFLATE_NONE || i - at > DEFLATE_WINDOW {
+                    break;
+                }
+                let length = (0..most).take_while(|&k| data[at + k] == data[i + k]).count();
+                if length > best {
+                    (best, distance) = (length, i - at);
+                    if length == most {
+                        break;
+                    }
+                }
+                // the table is reused, so older places only
+                let next = prev[at % DEFLATE_WINDOW];
+                if next == DEFLATE_NONE || next >= at {
+                    break;
+                }
+                at = next;
+            }
+        }
+        if best >= 3 {
+            deflate_match(&mut b, best as u32, distance as u32);
+            for k in i..i + best {
+                deflate_insert(data, &mut head, &mut prev, k);
+            }
+            i += best;
+        }
+        else {
+            deflate_symbol(&mut b, data[i] as u32);
+            deflate_insert(data, &mut head, &mut prev, i);
+            i += 1;
+        }
+    }
+    deflate_symbol(&mut b, 256);
+    if b.n > 0 {
+        b.out.push(b.acc as u8);
+    }
+    b.out.extend_from_slice(&zlib_adler32(data).to_be_bytes());
+    b.out
+}